@@ -0,0 +1,578 @@
+//! Linux kernel `video=` mode specifications.
+//!
+//! The kernel command line selects the mode of a connector with strings like:
+//!
+//! ```text
+//! video=HDMI-A-1:1920x1080@60
+//! ```
+//!
+//! Only the mode specification following the connector name is handled here:
+//!
+//! ```text
+//! <xres>x<yres>[M][R][-<bpp>][@<refresh>][i][m][e|D|d][,<option>...]
+//! ```
+//!
+//! Named modes like `NTSC` and the `tv_mode` option aren't supported.
+use std::{fmt, str::FromStr};
+
+use crate::{
+    cta::VideoFormat,
+    cvt, dmt,
+    mode::{Mode, Origin},
+};
+
+/// Refresh rate used when the mode specification doesn't contain any, as the
+/// kernel does.
+const DEFAULT_REFRESH_HZ: u32 = 60;
+
+/// Connector state forced by a mode specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Force {
+    /// Enable the connector, `e`
+    On,
+    /// Enable the connector with its digital output, `D`
+    OnDigital,
+    /// Disable the connector, `d`
+    Off,
+}
+
+/// Rotation of the output, in degrees counter-clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    _0,
+    _90,
+    _180,
+    _270,
+}
+
+/// Orientation of the panel within its casing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PanelOrientation {
+    Normal,
+    UpsideDown,
+    LeftSideUp,
+    RightSideUp,
+}
+
+/// Margins of analog TV outputs, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TvMargins {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+/// A kernel `video=` mode specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CmdlineMode {
+    pub width: i32,
+    pub height: i32,
+    /// Whether the timing is computed via CVT instead of being looked up, `M`
+    pub cvt: bool,
+    /// Whether the timing is computed via CVT with reduced blanking, `R`
+    pub reduced_blanking: bool,
+    /// Bits per pixel
+    pub bpp: Option<u32>,
+    /// Refresh rate in Hz
+    ///
+    /// For interlaced modes, DMT timings and CTA-861 video formats are looked
+    /// up by their field rate, while CVT is given the rate as a frame rate.
+    pub refresh_hz: Option<u32>,
+    /// `i`
+    pub interlaced: bool,
+    /// Whether the CVT timing includes margins, `m`
+    pub margins: bool,
+    pub force: Option<Force>,
+    pub tv_margins: TvMargins,
+    pub rotation: Rotation,
+    pub reflect_x: bool,
+    pub reflect_y: bool,
+    pub panel_orientation: Option<PanelOrientation>,
+}
+
+impl CmdlineMode {
+    /// Create a mode specification with only a resolution.
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            cvt: false,
+            reduced_blanking: false,
+            bpp: None,
+            refresh_hz: None,
+            interlaced: false,
+            margins: false,
+            force: None,
+            tv_margins: TvMargins::default(),
+            rotation: Rotation::_0,
+            reflect_x: false,
+            reflect_y: false,
+            panel_orientation: None,
+        }
+    }
+
+    /// Resolve the mode specification into a timing.
+    ///
+    /// Unless CVT is requested, a DMT timing or a CTA-861 video format with
+    /// the same resolution, refresh rate and scanning is used if one exists.
+    /// Otherwise, the timing is computed via CVT, with reduced blanking if
+    /// requested. Unlike the kernel, which falls back to GTF, `R` alone
+    /// selects CVT with reduced blanking. Without a refresh rate, 60 Hz is
+    /// assumed.
+    ///
+    /// Like the kernel, CVT takes the refresh rate of interlaced modes as a
+    /// frame rate, so `1600x900M@60i` yields a mode with 120 fields per
+    /// second.
+    pub fn mode(&self) -> Mode {
+        let refresh_hz = self.refresh_hz.unwrap_or(DEFAULT_REFRESH_HZ);
+
+        if !self.cvt && !self.reduced_blanking {
+            let exact = dmt::Timing::all()
+                .map(Mode::from)
+                .chain(VideoFormat::all().map(Mode::from))
+                .find(|mode| {
+                    mode.h_active == self.width
+                        && mode.v_active == self.height
+                        && mode.interlaced == self.interlaced
+                        && (mode.refresh_rate_mhz() + 500) / 1000 == refresh_hz as u64
+                });
+            if let Some(mode) = exact {
+                return mode;
+            }
+        }
+
+        self.cvt_mode(cvt::Timing::compute(self.cvt_options()))
+    }
+
+    /// CVT input parameters of the mode specification.
+    fn cvt_options(&self) -> cvt::Options {
+        let red_blank_ver = if self.reduced_blanking {
+            cvt::ReducedBlankingVersion::V1
+        } else {
+            cvt::ReducedBlankingVersion::None
+        };
+        let refresh_hz = self.refresh_hz.unwrap_or(DEFAULT_REFRESH_HZ) as f64;
+
+        cvt::Options {
+            int_rqd: self.interlaced,
+            margins_rqd: self.margins,
+            ..cvt::Options::new(red_blank_ver, self.width, self.height, refresh_hz)
+        }
+    }
+
+    /// Convert the CVT timing computed for the mode specification.
+    fn cvt_mode(&self, timing: cvt::Timing) -> Mode {
+        let mode = Mode::from(timing);
+        if self.interlaced {
+            // The vertical values of interlaced CVT timings are given per
+            // field.
+            Mode::from_fields(
+                mode.v_active,
+                mode.v_front,
+                mode.v_sync,
+                mode.v_front + mode.v_sync + mode.v_back,
+                true,
+                mode,
+            )
+        } else {
+            mode
+        }
+    }
+}
+
+impl From<Mode> for CmdlineMode {
+    /// Describe a mode by its resolution, rounded refresh rate and scanning.
+    ///
+    /// Modes computed via CVT, with or without reduced blanking version 1,
+    /// request CVT. Other modes resolve back to themselves only if they are
+    /// a DMT timing or a CTA-861 video format.
+    ///
+    /// The conversion is lossy. The kernel syntax only has integer refresh
+    /// rates, so modes at a 1000/1001 fractional rate resolve to the timing
+    /// at the nominal rate. Modes computed via CVT with reduced blanking
+    /// version 2 or 3 lose their origin, and resolve to a DMT timing, a
+    /// CTA-861 video format or a standard CVT timing.
+    fn from(mode: Mode) -> Self {
+        let (cvt, reduced_blanking) = match mode.origin() {
+            Origin::Cvt(cvt::ReducedBlankingVersion::None) => (true, false),
+            Origin::Cvt(cvt::ReducedBlankingVersion::V1) => (true, true),
+            _ => (false, false),
+        };
+
+        Self {
+            cvt,
+            reduced_blanking,
+            refresh_hz: Some(((mode.refresh_rate_mhz() + 500) / 1000) as u32),
+            interlaced: mode.interlaced,
+            ..CmdlineMode::new(mode.h_active, mode.v_active)
+        }
+    }
+}
+
+/// Parsing a mode specification failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseCmdlineModeError {
+    /// The resolution is missing or isn't valid
+    #[error("invalid resolution")]
+    InvalidResolution,
+    /// A value isn't a valid number
+    #[error("invalid {field}: {value:?}")]
+    InvalidNumber { field: &'static str, value: String },
+    /// A flag isn't supported
+    #[error("unknown flag {0:?}")]
+    UnknownFlag(char),
+    /// A flag is repeated, or conflicts with a previous one
+    #[error("duplicate flag {0:?}")]
+    DuplicateFlag(char),
+    /// An option isn't supported
+    #[error("unknown option {0:?}")]
+    UnknownOption(String),
+    /// The value of an option isn't valid
+    #[error("invalid value for {option}: {value:?}")]
+    InvalidOption { option: &'static str, value: String },
+}
+
+/// Split a string after its leading ASCII digits.
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// Parse a decimal number, made of ASCII digits only.
+fn parse_number(field: &'static str, value: &str) -> Result<u32, ParseCmdlineModeError> {
+    value
+        .parse()
+        .ok()
+        .filter(|_| value.bytes().all(|c| c.is_ascii_digit()))
+        .ok_or_else(|| ParseCmdlineModeError::InvalidNumber {
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Parse a non-zero decimal number, made of ASCII digits only.
+fn parse_positive(field: &'static str, value: &str) -> Result<u32, ParseCmdlineModeError> {
+    match parse_number(field, value)? {
+        0 => Err(ParseCmdlineModeError::InvalidNumber {
+            field,
+            value: value.to_string(),
+        }),
+        number => Ok(number),
+    }
+}
+
+/// Set a flag, failing if it is already set.
+fn set_flag(flag: &mut bool, c: char) -> Result<(), ParseCmdlineModeError> {
+    if *flag {
+        return Err(ParseCmdlineModeError::DuplicateFlag(c));
+    }
+    *flag = true;
+    Ok(())
+}
+
+impl FromStr for CmdlineMode {
+    type Err = ParseCmdlineModeError;
+
+    /// Parse a mode specification, without the connector name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, options) = match s.split_once(',') {
+            Some((spec, options)) => (spec, Some(options)),
+            None => (s, None),
+        };
+
+        let (width, rest) = split_digits(spec);
+        let rest = rest
+            .strip_prefix('x')
+            .ok_or(ParseCmdlineModeError::InvalidResolution)?;
+        let (height, mut rest) = split_digits(rest);
+        let resolution = |value: &str| {
+            value
+                .parse::<i32>()
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or(ParseCmdlineModeError::InvalidResolution)
+        };
+        let mut mode = CmdlineMode::new(resolution(width)?, resolution(height)?);
+
+        while let Some(c) = rest.chars().next().filter(|c| matches!(c, 'M' | 'R')) {
+            match c {
+                'M' => set_flag(&mut mode.cvt, c)?,
+                _ => set_flag(&mut mode.reduced_blanking, c)?,
+            }
+            rest = &rest[1..];
+        }
+        if let Some(bpp) = rest.strip_prefix('-') {
+            let (bpp, tail) = split_digits(bpp);
+            mode.bpp = Some(parse_positive("bpp", bpp)?);
+            rest = tail;
+        }
+        if let Some(refresh) = rest.strip_prefix('@') {
+            let (refresh, tail) = split_digits(refresh);
+            mode.refresh_hz = Some(parse_positive("refresh rate", refresh)?);
+            rest = tail;
+        }
+
+        for c in rest.chars() {
+            let force = match c {
+                'i' => {
+                    set_flag(&mut mode.interlaced, c)?;
+                    continue;
+                }
+                'm' => {
+                    set_flag(&mut mode.margins, c)?;
+                    continue;
+                }
+                'e' => Force::On,
+                'D' => Force::OnDigital,
+                'd' => Force::Off,
+                _ => return Err(ParseCmdlineModeError::UnknownFlag(c)),
+            };
+            if mode.force.is_some() {
+                return Err(ParseCmdlineModeError::DuplicateFlag(c));
+            }
+            mode.force = Some(force);
+        }
+
+        for option in options.into_iter().flat_map(|options| options.split(',')) {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (option, None),
+            };
+            match (name, value) {
+                ("margin_left", Some(value)) => {
+                    mode.tv_margins.left = parse_number("margin_left", value)?
+                }
+                ("margin_right", Some(value)) => {
+                    mode.tv_margins.right = parse_number("margin_right", value)?
+                }
+                ("margin_top", Some(value)) => {
+                    mode.tv_margins.top = parse_number("margin_top", value)?
+                }
+                ("margin_bottom", Some(value)) => {
+                    mode.tv_margins.bottom = parse_number("margin_bottom", value)?
+                }
+                ("rotate", Some(value)) => {
+                    mode.rotation = match value {
+                        "0" => Rotation::_0,
+                        "90" => Rotation::_90,
+                        "180" => Rotation::_180,
+                        "270" => Rotation::_270,
+                        _ => {
+                            return Err(ParseCmdlineModeError::InvalidOption {
+                                option: "rotate",
+                                value: value.to_string(),
+                            })
+                        }
+                    }
+                }
+                ("reflect_x", None) => mode.reflect_x = true,
+                ("reflect_y", None) => mode.reflect_y = true,
+                ("panel_orientation", Some(value)) => {
+                    mode.panel_orientation = Some(match value {
+                        "normal" => PanelOrientation::Normal,
+                        "upside_down" => PanelOrientation::UpsideDown,
+                        "left_side_up" => PanelOrientation::LeftSideUp,
+                        "right_side_up" => PanelOrientation::RightSideUp,
+                        _ => {
+                            return Err(ParseCmdlineModeError::InvalidOption {
+                                option: "panel_orientation",
+                                value: value.to_string(),
+                            })
+                        }
+                    })
+                }
+                _ => return Err(ParseCmdlineModeError::UnknownOption(option.to_string())),
+            }
+        }
+
+        Ok(mode)
+    }
+}
+
+impl fmt::Display for CmdlineMode {
+    /// Format the mode specification in the order of the kernel syntax.
+    /// Options are only printed if they differ from their default.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if self.cvt {
+            f.write_str("M")?;
+        }
+        if self.reduced_blanking {
+            f.write_str("R")?;
+        }
+        if let Some(bpp) = self.bpp {
+            write!(f, "-{bpp}")?;
+        }
+        if let Some(refresh_hz) = self.refresh_hz {
+            write!(f, "@{refresh_hz}")?;
+        }
+        if self.interlaced {
+            f.write_str("i")?;
+        }
+        if self.margins {
+            f.write_str("m")?;
+        }
+        if let Some(force) = self.force {
+            f.write_str(match force {
+                Force::On => "e",
+                Force::OnDigital => "D",
+                Force::Off => "d",
+            })?;
+        }
+
+        for (name, value) in [
+            ("margin_left", self.tv_margins.left),
+            ("margin_right", self.tv_margins.right),
+            ("margin_top", self.tv_margins.top),
+            ("margin_bottom", self.tv_margins.bottom),
+        ] {
+            if value != 0 {
+                write!(f, ",{name}={value}")?;
+            }
+        }
+        match self.rotation {
+            Rotation::_0 => {}
+            Rotation::_90 => f.write_str(",rotate=90")?,
+            Rotation::_180 => f.write_str(",rotate=180")?,
+            Rotation::_270 => f.write_str(",rotate=270")?,
+        }
+        if self.reflect_x {
+            f.write_str(",reflect_x")?;
+        }
+        if self.reflect_y {
+            f.write_str(",reflect_y")?;
+        }
+        if let Some(orientation) = self.panel_orientation {
+            f.write_str(match orientation {
+                PanelOrientation::Normal => ",panel_orientation=normal",
+                PanelOrientation::UpsideDown => ",panel_orientation=upside_down",
+                PanelOrientation::LeftSideUp => ",panel_orientation=left_side_up",
+                PanelOrientation::RightSideUp => ",panel_orientation=right_side_up",
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interlaced_cvt() {
+        let mode: CmdlineMode = "1600x900@60i".parse().unwrap();
+        let options = mode.cvt_options();
+        assert!(options.int_rqd);
+        assert_eq!(options.ip_freq_rqd, 60.0);
+
+        // Output of the CVT formula for 1600x900 interlaced at 60 frames per
+        // second, with vertical values per field.
+        let timing = cvt::Timing {
+            act_pixel_freq: 123.75,
+            total_active_pixels: 1600.0,
+            v_lines_rnd: 450.0,
+            h_front_porch: 96.0,
+            h_sync: 168.0,
+            h_back_porch: 264.0,
+            v_front_porch: 3.0,
+            v_sync: 5.0,
+            v_back_porch: 28.0,
+            act_frame_rate: 59.767,
+        };
+        let mode = mode.cvt_mode(timing);
+        assert!(mode.interlaced);
+        assert_eq!((mode.v_active, mode.v_total()), (900, 973));
+        assert_eq!(mode.refresh_rate_mhz(), 119_534);
+    }
+
+    #[test]
+    fn progressive_cvt() {
+        let options = "1600x900MR@75m"
+            .parse::<CmdlineMode>()
+            .unwrap()
+            .cvt_options();
+        assert_eq!(options.red_blank_ver, cvt::ReducedBlankingVersion::V1);
+        assert_eq!((options.h_pixels, options.v_lines), (1600, 900));
+        assert_eq!(options.ip_freq_rqd, 75.0);
+        assert!(!options.int_rqd);
+        assert!(options.margins_rqd);
+
+        let options = CmdlineMode::new(1600, 900).cvt_options();
+        assert_eq!(options.red_blank_ver, cvt::ReducedBlankingVersion::None);
+        assert_eq!(options.ip_freq_rqd, DEFAULT_REFRESH_HZ as f64);
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in [
+            "1920x1080",
+            "1920x1080@60",
+            "1920x1080MR-24@60",
+            "720x480@60ime",
+            "1024x768D",
+            "800x600d,margin_left=10,margin_bottom=20",
+            "1080x1920,rotate=90,reflect_x,panel_orientation=right_side_up",
+        ] {
+            let mode: CmdlineMode = s.parse().unwrap();
+            assert_eq!(mode.to_string(), s);
+        }
+
+        let mode: CmdlineMode = "1280x720MR-32@50ie,rotate=180,reflect_y".parse().unwrap();
+        assert_eq!(
+            mode,
+            CmdlineMode {
+                cvt: true,
+                reduced_blanking: true,
+                bpp: Some(32),
+                refresh_hz: Some(50),
+                interlaced: true,
+                force: Some(Force::On),
+                rotation: Rotation::_180,
+                reflect_y: true,
+                ..CmdlineMode::new(1280, 720)
+            }
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |s: &str| s.parse::<CmdlineMode>().unwrap_err();
+        assert_eq!(parse("1920"), ParseCmdlineModeError::InvalidResolution);
+        assert_eq!(parse("0x1080"), ParseCmdlineModeError::InvalidResolution);
+        assert_eq!(
+            parse("1920x1080@"),
+            ParseCmdlineModeError::InvalidNumber {
+                field: "refresh rate",
+                value: String::new()
+            }
+        );
+        assert_eq!(
+            parse("1920x1080-0"),
+            ParseCmdlineModeError::InvalidNumber {
+                field: "bpp",
+                value: "0".to_string()
+            }
+        );
+        assert_eq!(
+            parse("1920x1080MM"),
+            ParseCmdlineModeError::DuplicateFlag('M')
+        );
+        assert_eq!(
+            parse("1920x1080ed"),
+            ParseCmdlineModeError::DuplicateFlag('d')
+        );
+        assert_eq!(parse("1920x1080x"), ParseCmdlineModeError::UnknownFlag('x'));
+        assert_eq!(
+            parse("1920x1080,tv_mode=NTSC"),
+            ParseCmdlineModeError::UnknownOption("tv_mode=NTSC".to_string())
+        );
+        assert_eq!(
+            parse("1920x1080,rotate=45"),
+            ParseCmdlineModeError::InvalidOption {
+                option: "rotate",
+                value: "45".to_string()
+            }
+        );
+    }
+}
@@ -6,7 +6,12 @@ use std::marker::PhantomData;
 
 use libdisplay_info_derive::FFIFrom;
 
-use crate::{edid::ExtensionRef, ffi, FFIIter};
+use crate::{
+    edid::{checksum, encode_range, DetailedTimingDef, EncodeError, ExtensionRef},
+    ffi,
+    mode::Mode,
+    FFIIter,
+};
 
 /// EDID CTA-861 extension block.
 #[derive(Debug)]
@@ -63,6 +68,132 @@ impl<'ext> CTA<'ext> {
                 as *const *const ffi::edid::di_edid_detailed_timing_def
         })
     }
+
+    /// Get the video formats of the short video descriptors, in order.
+    ///
+    /// The formats of the video data blocks come first. They support RGB,
+    /// and YCbCr 4:2:0 if the YCbCr 4:2:0 capability map of the extension
+    /// says so. The map indexes the SVDs of all video data blocks of the
+    /// extension, in order, and doesn't apply to the other extensions. It is
+    /// ignored before libdisplay-info 0.3, which doesn't expose the index of
+    /// the SVDs. The formats of the YCbCr 4:2:0 video data blocks follow, and
+    /// only support YCbCr 4:2:0 unless they are also listed in a video data
+    /// block.
+    ///
+    /// SVDs with unknown VICs are skipped.
+    pub fn video_formats(&self) -> Vec<SvdVideoFormat> {
+        let cap_map = self
+            .data_blocks()
+            .iter()
+            .find_map(|data_block| data_block.ycbcr420_cap_map());
+
+        let svds: Vec<Svd> = self.video_svds().collect();
+        let supports_ycbcr420 = ycbcr420_cap_map_support(&svds, |index| {
+            cap_map
+                .as_ref()
+                .is_some_and(|cap_map| cap_map.di_cta_ycbcr420_cap_map_supported(index))
+        });
+
+        let mut formats: Vec<SvdVideoFormat> = Vec::new();
+        for (svd, supports_ycbcr420) in svds.iter().zip(supports_ycbcr420) {
+            let Some(format) = VideoFormat::from_vic(svd.vic) else {
+                continue;
+            };
+            formats.push(SvdVideoFormat {
+                format,
+                native: svd.native,
+                supports_rgb: true,
+                supports_ycbcr420,
+            });
+        }
+
+        for svd in self.ycbcr420_video_svds() {
+            let Some(format) = VideoFormat::from_vic(svd.vic) else {
+                continue;
+            };
+            match formats.iter_mut().find(|other| other.format.vic == svd.vic) {
+                Some(other) => {
+                    other.native |= svd.native;
+                    other.supports_ycbcr420 = true;
+                }
+                None => formats.push(SvdVideoFormat {
+                    format,
+                    native: svd.native,
+                    supports_rgb: false,
+                    supports_ycbcr420: true,
+                }),
+            }
+        }
+        formats
+    }
+
+    /// Iterate over the SVDs of all video data blocks, in order.
+    fn video_svds(&self) -> impl Iterator<Item = Svd> + '_ {
+        #[cfg(feature = "v0_3")]
+        let svds = self
+            .data_blocks()
+            .iter()
+            .filter_map(|data_block| data_block.video())
+            .flat_map(|video| video.svds());
+        #[cfg(not(feature = "v0_3"))]
+        let svds = self
+            .data_blocks()
+            .iter()
+            .flat_map(|data_block| data_block.svds());
+        svds
+    }
+
+    /// Iterate over the SVDs of all YCbCr 4:2:0 video data blocks, in order.
+    fn ycbcr420_video_svds(&self) -> impl Iterator<Item = Svd> + '_ {
+        #[cfg(feature = "v0_3")]
+        let svds = self
+            .data_blocks()
+            .iter()
+            .filter_map(|data_block| data_block.ycbcr420_video())
+            .flat_map(|video| video.svds());
+        #[cfg(not(feature = "v0_3"))]
+        let svds = self
+            .data_blocks()
+            .iter()
+            .flat_map(|data_block| data_block.ycbcr420_svds());
+        svds
+    }
+}
+
+/// Check which SVDs of the video data blocks support YCbCr 4:2:0, given
+/// whether the capability map marks an SVD index as supported.
+///
+/// The map indexes the SVDs as stored in the blocks, while libdisplay-info
+/// drops invalid SVDs. The original index of the SVDs accounts for them.
+#[cfg(feature = "v0_3")]
+fn ycbcr420_cap_map_support(svds: &[Svd], supported: impl Fn(usize) -> bool) -> Vec<bool> {
+    svds.iter()
+        .map(|svd| supported(svd.original_index as usize))
+        .collect()
+}
+
+/// Check which SVDs of the video data blocks support YCbCr 4:2:0.
+///
+/// Without the original index of the SVDs, their position in the capability
+/// map is unknown once libdisplay-info dropped an invalid SVD. None of them
+/// is reported as supporting YCbCr 4:2:0 via the map.
+#[cfg(not(feature = "v0_3"))]
+fn ycbcr420_cap_map_support(svds: &[Svd], _supported: impl Fn(usize) -> bool) -> Vec<bool> {
+    vec![false; svds.len()]
+}
+
+/// A video format of a short video descriptor, with the sampling modes it
+/// supports.
+#[derive(Debug, Copy, Clone)]
+pub struct SvdVideoFormat {
+    pub format: VideoFormat,
+    /// Whether the format is a native format of the display
+    pub native: bool,
+    /// Whether the format supports RGB, and YCbCr 4:4:4 and 4:2:2 if the
+    /// sink supports them
+    pub supports_rgb: bool,
+    /// Whether the format supports YCbCr 4:2:0
+    pub supports_ycbcr420: bool,
 }
 
 /// CTA video format picture aspect ratio.
@@ -118,6 +249,56 @@ impl VideoFormat {
             Some(VideoFormat::from(unsafe { *video_format }))
         }
     }
+
+    /// Iterate over all CTA-861 video formats, by increasing VIC.
+    pub fn all() -> impl Iterator<Item = VideoFormat> {
+        (1..=u8::MAX).filter_map(VideoFormat::from_vic)
+    }
+
+    /// Find the CTA-861 video formats of a mode.
+    ///
+    /// Formats whose rate is a multiple of 6 Hz also match at their 1000/1001
+    /// fractional rate, as allowed by CTA-861. Sync polarities are ignored.
+    /// Formats with the same timing but different picture aspect ratios all
+    /// match, unless `picture_aspect_ratio` selects one of them.
+    pub fn find(
+        mode: &Mode,
+        picture_aspect_ratio: Option<VideoFormatPictureAspectRatio>,
+    ) -> Vec<VideoFormat> {
+        VideoFormat::all()
+            .filter(|format| {
+                picture_aspect_ratio.map_or(true, |aspect_ratio| {
+                    format.picture_aspect_ratio == aspect_ratio
+                })
+            })
+            .filter(|format| matches_format(mode, Mode::from(*format)))
+            .collect()
+    }
+
+    /// Get the HDMI VIC of the format.
+    ///
+    /// HDMI 1.4b sinks expect the 4K formats which predate CTA-861-F to be
+    /// signalled in the HDMI Vendor-Specific InfoFrame by their HDMI VIC,
+    /// with a zero VIC in the AVI InfoFrame. Returns `None` for other formats.
+    #[cfg(feature = "v0_3")]
+    pub fn hdmi_vic(&self) -> Option<u8> {
+        HDMI_VIC_TO_VIC
+            .iter()
+            .find(|(_, vic)| *vic == self.vic)
+            .map(|(hdmi_vic, _)| *hdmi_vic)
+    }
+}
+
+/// Check whether a mode matches the timing of a video format, at its nominal
+/// rate or, for rates which are a multiple of 6 Hz, at its 1000/1001
+/// fractional rate.
+fn matches_format(mode: &Mode, nominal: Mode) -> bool {
+    let fractional = Mode {
+        pixel_clock_hz: nominal.pixel_clock_hz * 1000 / 1001,
+        ..nominal
+    };
+    let has_fractional = (nominal.refresh_rate_mhz() + 500) / 1000 % 6 == 0;
+    mode.matches(&nominal) || has_fractional && mode.matches(&fractional)
 }
 
 /// Miscellaneous EDID CTA flags, defined in section 7.3.3.
@@ -384,6 +565,24 @@ impl DataBlockRef {
         })
     }
 
+    /// Get the speaker of each channel from the Speaker Locations, indexed by
+    /// channel index.
+    ///
+    /// Channels without an active speaker location are `None`. Returns an
+    /// empty list if the data block tag is not
+    /// DI_CTA_DATA_BLOCK_SPEAKER_LOCATION.
+    #[cfg_attr(not(docsrs), cfg(all(feature = "v0_2", not(feature = "v0_3"))))]
+    #[cfg_attr(docsrs, cfg(any(feature = "v0_2", feature = "v0_3")))]
+    pub fn channel_positions(&self) -> Vec<Option<SpeakerPlacement>> {
+        channel_positions(self.speaker_locations().map(|location| {
+            (
+                location.channel_index,
+                location.is_active,
+                location.speaker_id,
+            )
+        }))
+    }
+
     /// Get the Speaker Locations from a CTA data block.
     ///
     /// Returns `None` if the data block tag is not DI_CTA_DATA_BLOCK_SPEAKER_LOCATION.
@@ -1788,8 +1987,262 @@ impl SpeakerLocationBlockRef {
     pub fn locations(&self) -> impl Iterator<Item = SpeakerLocationDescriptor> {
         FFIIter::new(unsafe { (*self.0).locations })
     }
+
+    /// Get the speaker of each channel, indexed by channel index.
+    ///
+    /// Channels without an active speaker location descriptor are `None`.
+    pub fn channel_positions(&self) -> Vec<Option<SpeakerPlacement>> {
+        channel_positions(self.locations().map(|location| {
+            (
+                location.channel_index,
+                location.is_active,
+                location.speaker_id,
+            )
+        }))
+    }
+}
+
+/// Map speaker locations, given as channel index, activity and speaker, to
+/// the speaker of each channel.
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+fn channel_positions(
+    locations: impl Iterator<Item = (std::os::raw::c_int, bool, SpeakerPlacement)>,
+) -> Vec<Option<SpeakerPlacement>> {
+    let mut positions = Vec::new();
+    for (channel_index, _, speaker) in locations.filter(|(_, is_active, _)| *is_active) {
+        let Ok(index) = usize::try_from(channel_index) else {
+            continue;
+        };
+        if positions.len() <= index {
+            positions.resize(index + 1, None);
+        }
+        positions[index] = Some(speaker);
+    }
+    positions
+}
+
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+impl SpeakerAllocation {
+    /// Get the speakers which are present, in the order of the speaker
+    /// allocation bits.
+    pub fn positions(&self) -> Vec<SpeakerPlacement> {
+        use SpeakerPlacement::*;
+
+        [
+            (self.fl_fr, &[FL, FR][..]),
+            (self.lfe1, &[LFE1]),
+            (self.fc, &[FC]),
+            (self.bl_br, &[BL, BR]),
+            (self.bc, &[BC]),
+            (self.flc_frc, &[FLC, FRC]),
+            (self.flw_frw, &[FLW, FRW]),
+            (self.tpfl_tpfr, &[TPFL, TPFR]),
+            (self.tpc, &[TPC]),
+            (self.tpfc, &[TPFC]),
+            (self.ls_rs, &[LS, RS]),
+            (self.lfe2, &[LFE2]),
+            (self.tpbc, &[TPBC]),
+            (self.sil_sir, &[SIL, SIR]),
+            (self.tpsil_tpsir, &[TPSIL, TPSIR]),
+            (self.tpbl_tpbr, &[TPBL, TPBR]),
+            (self.btfc, &[BTFC]),
+            (self.btfl_btfr, &[BTFL, BRFR]),
+        ]
+        .into_iter()
+        .filter(|(present, _)| *present)
+        .flat_map(|(_, speakers)| speakers.iter().copied())
+        .collect()
+    }
+
+    /// Get the channel allocation best fitting a source with the given
+    /// number of channels.
+    ///
+    /// Only allocations whose speakers are all present are considered. The
+    /// allocation with as many speakers as channels is preferred, otherwise
+    /// the one with the most speakers below it, which requires a downmix.
+    /// Common layouts, stereo, 2.1, 3.0, 4.0, 4.1, 5.0, 5.1, 6.1 and 7.1,
+    /// win over others with the same speaker count. Falls back to stereo.
+    pub fn channel_allocation(&self, channels: usize) -> ChannelAllocation {
+        let present = self.positions();
+        let candidates = CHANNEL_ALLOCATION_PRIORITY
+            .iter()
+            .copied()
+            .chain(
+                (0..CHANNEL_ALLOCATIONS.len() as u8)
+                    .filter(|ca| !CHANNEL_ALLOCATION_PRIORITY.contains(ca)),
+            )
+            .map(|ca| ChannelAllocation { ca })
+            .filter(|allocation| {
+                // FL and FR are mandatory, even if not advertised.
+                allocation
+                    .positions()
+                    .iter()
+                    .skip(2)
+                    .all(|speaker| present.contains(speaker))
+            })
+            .collect::<Vec<_>>();
+
+        let speaker_count = |allocation: &ChannelAllocation| allocation.positions().len();
+        candidates
+            .iter()
+            .find(|allocation| speaker_count(allocation) == channels)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .filter(|allocation| speaker_count(allocation) < channels)
+                    // The last maximum wins, keep the one with the highest priority.
+                    .rev()
+                    .max_by_key(|allocation| speaker_count(allocation))
+            })
+            .copied()
+            .unwrap_or(ChannelAllocation { ca: 0 })
+    }
+}
+
+/// Audio channel allocation, the CA field of the audio InfoFrame, defined in
+/// section 6.6.2.
+///
+/// Channels 1 and 2 always carry FL and FR, the channel allocation selects
+/// the speakers of channels 3 to 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+pub struct ChannelAllocation {
+    ca: u8,
 }
 
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+impl ChannelAllocation {
+    /// Get a channel allocation from its CA value.
+    ///
+    /// Returns `None` if the CA value is reserved.
+    pub fn from_ca(ca: u8) -> Option<Self> {
+        (usize::from(ca) < CHANNEL_ALLOCATIONS.len()).then_some(ChannelAllocation { ca })
+    }
+
+    /// Iterate over all channel allocations, by increasing CA value.
+    pub fn all() -> impl Iterator<Item = ChannelAllocation> {
+        (0..CHANNEL_ALLOCATIONS.len() as u8).map(|ca| ChannelAllocation { ca })
+    }
+
+    /// Find the channel allocation with exactly the given speakers, in any
+    /// order.
+    pub fn from_positions(positions: &[SpeakerPlacement]) -> Option<Self> {
+        ChannelAllocation::all().find(|allocation| {
+            let speakers = allocation.positions();
+            speakers.len() == positions.len()
+                && speakers.iter().all(|speaker| positions.contains(speaker))
+        })
+    }
+
+    /// Get the CA value, as sent in the audio InfoFrame.
+    pub fn ca(&self) -> u8 {
+        self.ca
+    }
+
+    /// Get the speaker of each of the 8 channels.
+    pub fn channels(&self) -> [Option<SpeakerPlacement>; 8] {
+        let mut channels = [None; 8];
+        channels[0] = Some(SpeakerPlacement::FL);
+        channels[1] = Some(SpeakerPlacement::FR);
+        channels[2..].copy_from_slice(&CHANNEL_ALLOCATIONS[usize::from(self.ca)]);
+        channels
+    }
+
+    /// Get the number of channels to transmit, up to the last one carrying
+    /// a speaker. This is one more than the channel count field of the
+    /// audio InfoFrame.
+    pub fn channel_count(&self) -> usize {
+        self.channels()
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |last| last + 1)
+    }
+
+    /// Get the speakers, in channel order. Channels without a speaker are
+    /// skipped.
+    pub fn positions(&self) -> Vec<SpeakerPlacement> {
+        self.channels().into_iter().flatten().collect()
+    }
+}
+
+/// Channel allocations of the common layouts, from the fewest speakers.
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+const CHANNEL_ALLOCATION_PRIORITY: [u8; 9] = [0x00, 0x01, 0x02, 0x08, 0x09, 0x0a, 0x0b, 0x0f, 0x13];
+
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+macro_rules! ca {
+    (@speaker -) => {
+        None
+    };
+    (@speaker $speaker:ident) => {
+        Some(SpeakerPlacement::$speaker)
+    };
+    ($($speaker:tt)*) => {
+        [$(ca!(@speaker $speaker)),*]
+    };
+}
+
+/// Speakers of channels 3 to 8 of each channel allocation, indexed by CA
+/// value, defined in table 34.
+///
+/// The rear speakers, RL/RR and RC in earlier revisions of the standard, are
+/// BL/BR and BC. The rear center speakers RLC/RRC of CA 0x10 to 0x13 are
+/// LS/RS, as in 7.1 layouts.
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+#[rustfmt::skip]
+static CHANNEL_ALLOCATIONS: [[Option<SpeakerPlacement>; 6]; 0x32] = [
+    ca!(-    -  -  -  -    -   ), // 0x00
+    ca!(LFE1 -  -  -  -    -   ), // 0x01
+    ca!(-    FC -  -  -    -   ), // 0x02
+    ca!(LFE1 FC -  -  -    -   ), // 0x03
+    ca!(-    -  BC -  -    -   ), // 0x04
+    ca!(LFE1 -  BC -  -    -   ), // 0x05
+    ca!(-    FC BC -  -    -   ), // 0x06
+    ca!(LFE1 FC BC -  -    -   ), // 0x07
+    ca!(-    -  BL BR -    -   ), // 0x08
+    ca!(LFE1 -  BL BR -    -   ), // 0x09
+    ca!(-    FC BL BR -    -   ), // 0x0a
+    ca!(LFE1 FC BL BR -    -   ), // 0x0b
+    ca!(-    -  BL BR BC   -   ), // 0x0c
+    ca!(LFE1 -  BL BR BC   -   ), // 0x0d
+    ca!(-    FC BL BR BC   -   ), // 0x0e
+    ca!(LFE1 FC BL BR BC   -   ), // 0x0f
+    ca!(-    -  BL BR LS   RS  ), // 0x10
+    ca!(LFE1 -  BL BR LS   RS  ), // 0x11
+    ca!(-    FC BL BR LS   RS  ), // 0x12
+    ca!(LFE1 FC BL BR LS   RS  ), // 0x13
+    ca!(-    -  -  -  FLC  FRC ), // 0x14
+    ca!(LFE1 -  -  -  FLC  FRC ), // 0x15
+    ca!(-    FC -  -  FLC  FRC ), // 0x16
+    ca!(LFE1 FC -  -  FLC  FRC ), // 0x17
+    ca!(-    -  BC -  FLC  FRC ), // 0x18
+    ca!(LFE1 -  BC -  FLC  FRC ), // 0x19
+    ca!(-    FC BC -  FLC  FRC ), // 0x1a
+    ca!(LFE1 FC BC -  FLC  FRC ), // 0x1b
+    ca!(-    -  BL BR FLC  FRC ), // 0x1c
+    ca!(LFE1 -  BL BR FLC  FRC ), // 0x1d
+    ca!(-    FC BL BR FLC  FRC ), // 0x1e
+    ca!(LFE1 FC BL BR FLC  FRC ), // 0x1f
+    ca!(-    FC BL BR TPFC -   ), // 0x20
+    ca!(LFE1 FC BL BR TPFC -   ), // 0x21
+    ca!(-    FC BL BR -    TPC ), // 0x22
+    ca!(LFE1 FC BL BR -    TPC ), // 0x23
+    ca!(-    -  BL BR TPFL TPFR), // 0x24
+    ca!(LFE1 -  BL BR TPFL TPFR), // 0x25
+    ca!(-    -  BL BR FLW  FRW ), // 0x26
+    ca!(LFE1 -  BL BR FLW  FRW ), // 0x27
+    ca!(-    FC BL BR BC   TPC ), // 0x28
+    ca!(LFE1 FC BL BR BC   TPC ), // 0x29
+    ca!(-    FC BL BR BC   TPFC), // 0x2a
+    ca!(LFE1 FC BL BR BC   TPFC), // 0x2b
+    ca!(-    FC BL BR TPFC TPC ), // 0x2c
+    ca!(LFE1 FC BL BR TPFC TPC ), // 0x2d
+    ca!(-    FC BL BR TPFL TPFR), // 0x2e
+    ca!(LFE1 FC BL BR TPFL TPFR), // 0x2f
+    ca!(-    FC BL BR FLW  FRW ), // 0x30
+    ca!(LFE1 FC BL BR FLW  FRW ), // 0x31
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FFIFrom)]
 #[ffi(ffi::cta::di_cta_svr_type)]
 #[repr(u32)]
@@ -1838,8 +2291,35 @@ impl HdmiVideoFormat {
             ffi::cta::di_cta_hdmi_video_format_from_hdmi_vic(hdmi_vic)
         })
     }
+
+    /// Iterate over all HDMI video formats, by increasing HDMI VIC.
+    pub fn all() -> impl Iterator<Item = HdmiVideoFormat> {
+        (1..=u8::MAX).filter_map(HdmiVideoFormat::from_vic)
+    }
+
+    /// Find the HDMI video format of a mode.
+    ///
+    /// Formats whose rate is a multiple of 6 Hz also match at their 1000/1001
+    /// fractional rate, like [`VideoFormat::find`]. Sync polarities are
+    /// ignored.
+    pub fn find(mode: &Mode) -> Option<HdmiVideoFormat> {
+        HdmiVideoFormat::all().find(|format| matches_format(mode, Mode::from(*format)))
+    }
+
+    /// Get the VIC of the equivalent CTA-861 video format.
+    pub fn cta_vic(&self) -> Option<u8> {
+        HDMI_VIC_TO_VIC
+            .iter()
+            .find(|(hdmi_vic, _)| *hdmi_vic == self.vic)
+            .map(|(_, vic)| *vic)
+    }
 }
 
+/// HDMI VICs and the VICs of the equivalent CTA-861 video formats, defined
+/// in HDMI 2.0 section 10.1.
+#[cfg(feature = "v0_3")]
+const HDMI_VIC_TO_VIC: [(u8, u8); 4] = [(1, 95), (2, 94), (3, 93), (4, 98)];
+
 /// HDR10+ Vendor-Specific Video Data Block
 #[derive(Debug, Copy, Clone, FFIFrom)]
 #[ffi(ffi::cta::di_cta_hdr10plus_block)]
@@ -2054,7 +2534,7 @@ pub enum HdmiDscMaxSlices {
 }
 
 /// Display Stream Compression (DSC) support.
-#[derive(Debug, Copy, Clone, FFIFrom)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FFIFrom)]
 #[ffi(ffi::cta::di_cta_hdmi_dsc)]
 #[cfg(feature = "v0_3")]
 pub struct HdmiDsc {
@@ -2140,3 +2620,908 @@ impl VideoFormatPrefBlockRef {
         FFIIter::new(unsafe { (*self.0).svrs })
     }
 }
+
+/// Data block tags, as defined in table 54.
+const TAG_AUDIO: u8 = 1;
+const TAG_VIDEO: u8 = 2;
+#[cfg(feature = "v0_3")]
+const TAG_VENDOR: u8 = 3;
+const TAG_SPEAKER_ALLOC: u8 = 4;
+const TAG_EXTENDED: u8 = 7;
+
+/// Extended data block tags, as defined in table 55.
+const EXT_TAG_VIDEO_CAP: u8 = 0;
+const EXT_TAG_COLORIMETRY: u8 = 5;
+const EXT_TAG_HDR_STATIC_METADATA: u8 = 6;
+const EXT_TAG_YCBCR420: u8 = 14;
+const EXT_TAG_YCBCR420_CAP_MAP: u8 = 15;
+#[cfg(feature = "v0_3")]
+const EXT_TAG_HDMI_SINK_CAP: u8 = 0x79;
+
+/// IEEE OUI of HDMI Licensing, LLC.
+#[cfg(feature = "v0_3")]
+const OUI_HDMI: [u8; 3] = [0x03, 0x0c, 0x00];
+/// IEEE OUI of the HDMI Forum.
+#[cfg(feature = "v0_3")]
+const OUI_HDMI_FORUM: [u8; 3] = [0xd8, 0x5d, 0xc4];
+
+impl Svd {
+    pub(crate) fn encode(&self) -> Result<u8, EncodeError> {
+        match self.vic {
+            1..=64 => Ok((self.native as u8) << 7 | self.vic),
+            65..=127 | 193..=255 if !self.native => Ok(self.vic),
+            _ => Err(EncodeError::OutOfRange("vic")),
+        }
+    }
+}
+
+impl Sad {
+    fn encode(&self) -> Result<[u8; 3], EncodeError> {
+        let (code, ext_code) = match self.format {
+            AudioFormat::LPCM => (1, 0),
+            AudioFormat::AC3 => (2, 0),
+            AudioFormat::MPEG1 => (3, 0),
+            AudioFormat::MP3 => (4, 0),
+            AudioFormat::MPEG2 => (5, 0),
+            AudioFormat::AAC_LC => (6, 0),
+            AudioFormat::DTS => (7, 0),
+            AudioFormat::ATRAC => (8, 0),
+            AudioFormat::ONE_BIT_AUDIO => (9, 0),
+            AudioFormat::ENHANCED_AC3 => (10, 0),
+            AudioFormat::DTS_HD => (11, 0),
+            AudioFormat::MAT => (12, 0),
+            AudioFormat::DST => (13, 0),
+            AudioFormat::WMA_PRO => (14, 0),
+            AudioFormat::MPEG4_HE_AAC => (15, 4),
+            AudioFormat::MPEG4_HE_AAC_V2 => (15, 5),
+            AudioFormat::MPEG4_AAC_LC => (15, 6),
+            AudioFormat::DRA => (15, 7),
+            AudioFormat::MPEG4_HE_AAC_MPEG_SURROUND => (15, 8),
+            AudioFormat::MPEG4_AAC_LC_MPEG_SURROUND => (15, 10),
+            AudioFormat::MPEGH_3D => (15, 11),
+            AudioFormat::AC4 => (15, 12),
+            AudioFormat::LPCM_3D => (15, 13),
+        };
+
+        let mut data = [code << 3, 0, 0];
+
+        match self.format {
+            AudioFormat::MPEGH_3D => {
+                if let Some(mpegh_3d) = self.mpegh_3d {
+                    data[0] |= mpegh_3d.level as u8 & 0x7;
+                    data[2] |= (mpegh_3d.baseline_profile as u8) << 1
+                        | mpegh_3d.low_complexity_profile as u8;
+                }
+            }
+            AudioFormat::AC4 => {}
+            AudioFormat::LPCM_3D => {
+                // The channel count is split over three bit fields.
+                let channels: u8 =
+                    encode_range(self.max_channels.unwrap_or(1) - 1, 0, 31, "max_channels")?;
+                data[0] |= ((channels >> 3) & 0x1) << 7 | channels & 0x7;
+                data[1] |= ((channels >> 4) & 0x1) << 7;
+            }
+            _ => {
+                let channels: u8 =
+                    encode_range(self.max_channels.unwrap_or(1) - 1, 0, 7, "max_channels")?;
+                data[0] |= channels;
+            }
+        }
+
+        if let Some(rates) = self.supported_sample_rates {
+            data[1] |= (rates.has_192_khz as u8) << 6
+                | (rates.has_176_4_khz as u8) << 5
+                | (rates.has_96_khz as u8) << 4
+                | (rates.has_88_2_khz as u8) << 3
+                | (rates.has_48_khz as u8) << 2
+                | (rates.has_44_1_khz as u8) << 1
+                | rates.has_32_khz as u8;
+        }
+
+        data[2] |= ext_code << 3;
+        if let Some(lpcm) = self.lpcm {
+            data[2] |= (lpcm.has_sample_size_24_bits as u8) << 2
+                | (lpcm.has_sample_size_20_bits as u8) << 1
+                | lpcm.has_sample_size_16_bits as u8;
+        }
+        if let Some(max_bitrate_kbs) = self.max_bitrate_kbs {
+            data[2] = encode_range(max_bitrate_kbs / 8, 0, 0xff, "max_bitrate_kbs")?;
+        }
+        if let Some(mpeg_aac) = self.mpeg_aac {
+            data[2] |= (mpeg_aac.has_frame_length_1024 as u8) << 2
+                | (mpeg_aac.has_frame_length_960 as u8) << 1;
+        }
+        if let Some(mpeg_surround) = self.mpeg_surround {
+            data[2] |=
+                (mpeg_surround.signaling == SadMpegSurroundSignaling::ImplicitAndExplicit) as u8;
+        }
+        if let Some(mpeg_aac_le) = self.mpeg_aac_le {
+            data[2] |= mpeg_aac_le.supports_multichannel_sound as u8;
+        }
+        if let Some(enhanced_ac3) = self.enhanced_ac3 {
+            data[2] |= (enhanced_ac3.supports_joint_object_coding_ACMOD28 as u8) << 1
+                | enhanced_ac3.supports_joint_object_coding as u8;
+        }
+        if let Some(mat) = self.mat {
+            data[2] |= (!mat.requires_hash_calculation as u8) << 1
+                | mat.supports_object_audio_and_channel_based as u8;
+        }
+        if let Some(wma_pro) = self.wma_pro {
+            data[2] |= encode_range::<u8>(wma_pro.profile, 0, 0x7, "profile")?;
+        }
+
+        Ok(data)
+    }
+}
+
+impl SpeakerAllocation {
+    fn encode(&self) -> [u8; 3] {
+        [
+            (self.flw_frw as u8) << 7
+                | (self.flc_frc as u8) << 5
+                | (self.bc as u8) << 4
+                | (self.bl_br as u8) << 3
+                | (self.fc as u8) << 2
+                | (self.lfe1 as u8) << 1
+                | self.fl_fr as u8,
+            (self.tpsil_tpsir as u8) << 7
+                | (self.sil_sir as u8) << 6
+                | (self.tpbc as u8) << 5
+                | (self.lfe2 as u8) << 4
+                | (self.ls_rs as u8) << 3
+                | (self.tpfc as u8) << 2
+                | (self.tpc as u8) << 1
+                | self.tpfl_tpfr as u8,
+            (self.btfl_btfr as u8) << 2 | (self.btfc as u8) << 1 | self.tpbl_tpbr as u8,
+        ]
+    }
+}
+
+impl VideoCapOverUnderscan {
+    fn encode(&self) -> u8 {
+        match self {
+            VideoCapOverUnderscan::UnknownOverUnderscan => 0,
+            VideoCapOverUnderscan::AlwaysOverscan => 1,
+            VideoCapOverUnderscan::AlwaysUnderscan => 2,
+            VideoCapOverUnderscan::BothOverUnderscan => 3,
+        }
+    }
+}
+
+impl VideoCapBlock {
+    fn encode(&self) -> u8 {
+        (self.selectable_ycc_quantization_range as u8) << 7
+            | (self.selectable_rgb_quantization_range as u8) << 6
+            | self.pt_over_underscan.encode() << 4
+            | self.it_over_underscan.encode() << 2
+            | self.ce_over_underscan.encode()
+    }
+}
+
+impl ColorimetryBlock {
+    fn encode(&self) -> [u8; 2] {
+        [
+            (self.bt2020_rgb as u8) << 7
+                | (self.bt2020_ycc as u8) << 6
+                | (self.bt2020_cycc as u8) << 5
+                | (self.oprgb as u8) << 4
+                | (self.opycc_601 as u8) << 3
+                | (self.sycc_601 as u8) << 2
+                | (self.xvycc_709 as u8) << 1
+                | self.xvycc_601 as u8,
+            (self.ictcp as u8) << 7 | (self.st2113_rgb as u8) << 6,
+        ]
+    }
+}
+
+impl HdrStaticMetadataBlock {
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut data = vec![0u8; 2];
+        if let Some(eotfs) = self.eotfs {
+            data[0] = (eotfs.hlg as u8) << 3
+                | (eotfs.pq as u8) << 2
+                | (eotfs.traditional_hdr as u8) << 1
+                | eotfs.traditional_sdr as u8;
+        }
+        if let Some(descriptors) = self.descriptors {
+            data[1] = descriptors.type1 as u8;
+        }
+
+        // Maximum luminances are encoded as 50 * 2 ^ (CV / 32) cd/m².
+        let encode_max = |luminance: Option<f32>, field| match luminance {
+            Some(luminance) => encode_range::<u8>(
+                (32.0 * (luminance as f64 / 50.0).log2()).round() as i64,
+                0,
+                0xff,
+                field,
+            ),
+            None => Ok(0),
+        };
+        let max_luminance = encode_max(
+            self.desired_content_max_luminance,
+            "desired_content_max_luminance",
+        )?;
+        let max_frame_avg_luminance = encode_max(
+            self.desired_content_max_frame_avg_luminance,
+            "desired_content_max_frame_avg_luminance",
+        )?;
+        // The minimum luminance is relative to the maximum luminance:
+        // max * (CV / 255) ^ 2 / 100 cd/m².
+        let min_luminance = match (
+            self.desired_content_min_luminance,
+            self.desired_content_max_luminance,
+        ) {
+            (Some(min), Some(max)) => encode_range::<u8>(
+                (255.0 * (100.0 * min as f64 / max as f64).sqrt()).round() as i64,
+                0,
+                0xff,
+                "desired_content_min_luminance",
+            )?,
+            (Some(_), None) => {
+                return Err(EncodeError::OutOfRange("desired_content_min_luminance"))
+            }
+            (None, _) => 0,
+        };
+
+        let luminances = [max_luminance, max_frame_avg_luminance, min_luminance];
+        let len = luminances
+            .iter()
+            .rposition(|cv| *cv != 0)
+            .map_or(0, |i| i + 1);
+        data.extend_from_slice(&luminances[..len]);
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "v0_3")]
+impl HdmiFrl {
+    fn encode(&self) -> u8 {
+        match self {
+            HdmiFrl::Unsupported => 0,
+            HdmiFrl::_3GBps3Lanes => 1,
+            HdmiFrl::_6GBps3Lanes => 2,
+            HdmiFrl::_6GBps4Lanes => 3,
+            HdmiFrl::_8GBps4Lanes => 4,
+            HdmiFrl::_10GBps4Lanes => 5,
+            HdmiFrl::_12GBps4Lanes => 6,
+        }
+    }
+}
+
+#[cfg(feature = "v0_3")]
+impl HdmiDscMaxSlices {
+    fn encode(&self) -> u8 {
+        match self {
+            HdmiDscMaxSlices::Unsupported => 0,
+            HdmiDscMaxSlices::_1_340MHZ => 1,
+            HdmiDscMaxSlices::_2_340MHZ => 2,
+            HdmiDscMaxSlices::_4_340MHZ => 3,
+            HdmiDscMaxSlices::_8_340MHZ => 4,
+            HdmiDscMaxSlices::_8_400MHZ => 5,
+            HdmiDscMaxSlices::_12_400MHZ => 6,
+            HdmiDscMaxSlices::_16_400MHZ => 7,
+        }
+    }
+}
+
+#[cfg(feature = "v0_3")]
+impl VendorHdmiBlock {
+    fn encode(&self, vics: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        // Video latencies are encoded as ms / 2 + 1, 255 means no video support.
+        let latency = |supported: bool, latency_ms: i32, field| {
+            if supported {
+                encode_range::<u8>(latency_ms / 2 + 1, 1, 251, field)
+            } else {
+                Ok(0xff)
+            }
+        };
+
+        let mut data = OUI_HDMI.to_vec();
+        data.extend_from_slice(&self.source_phys_addr.to_be_bytes());
+        data.push(
+            (self.supports_ai as u8) << 7
+                | (self.supports_dc_48bit as u8) << 6
+                | (self.supports_dc_36bit as u8) << 5
+                | (self.supports_dc_30bit as u8) << 4
+                | (self.supports_dc_y444 as u8) << 3
+                | self.supports_dvi_dual as u8,
+        );
+        data.push(encode_range(
+            self.max_tmds_clock / 5,
+            0,
+            0xff,
+            "max_tmds_clock",
+        )?);
+        data.push(
+            (self.has_latency as u8) << 7
+                | ((self.has_latency && self.has_interlaced_latency) as u8) << 6
+                | (!vics.is_empty() as u8) << 5
+                | (self.supports_content_game as u8) << 3
+                | (self.supports_content_cinema as u8) << 2
+                | (self.supports_content_photo as u8) << 1
+                | self.supports_content_graphics as u8,
+        );
+        if self.has_latency {
+            data.push(latency(
+                self.supports_progressive_video,
+                self.progressive_video_latency,
+                "progressive_video_latency",
+            )?);
+            data.push(latency(
+                self.supports_progressive_audio,
+                self.progressive_audio_latency,
+                "progressive_audio_latency",
+            )?);
+            if self.has_interlaced_latency {
+                data.push(latency(
+                    self.supports_interlaced_video,
+                    self.interlaced_video_latency,
+                    "interlaced_video_latency",
+                )?);
+                data.push(latency(
+                    self.supports_interlaced_audio,
+                    self.interlaced_audio_latency,
+                    "interlaced_audio_latency",
+                )?);
+            }
+        }
+        if !vics.is_empty() {
+            let len: u8 = encode_range(vics.len() as i64, 0, 7, "vics")?;
+            data.push(0);
+            data.push(len << 5);
+            data.extend_from_slice(vics);
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "v0_3")]
+impl HdmiScds {
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let tmds_char_rate = if self.max_tmds_char_rate_mhz > 340 {
+            encode_range(
+                self.max_tmds_char_rate_mhz / 5,
+                0,
+                0xff,
+                "max_tmds_char_rate_mhz",
+            )?
+        } else {
+            0
+        };
+
+        let mut data = vec![
+            encode_range(self.version, 0, 0xff, "version")?,
+            tmds_char_rate,
+            (self.supports_scdc as u8) << 7
+                | (self.supports_scdc_read_request as u8) << 6
+                | (self.supports_cable_status as u8) << 5
+                | (self.supports_ccbpci as u8) << 4
+                | (self.supports_lte_340mcsc_scramble as u8) << 3
+                | (self.supports_3d_independent_view as u8) << 2
+                | (self.supports_3d_dual_view as u8) << 1
+                | self.supports_3d_osd_disparity as u8,
+            self.max_frl_rate.encode() << 4
+                | (self.supports_uhd_vic as u8) << 3
+                | (self.supports_dc_48bit_420 as u8) << 2
+                | (self.supports_dc_36bit_420 as u8) << 1
+                | self.supports_dc_30bit_420 as u8,
+        ];
+
+        let features = (self.supports_fapa_end_extended as u8) << 7
+            | (self.supports_qms as u8) << 6
+            | (self.m_delta as u8) << 5
+            | (self.supports_cinema_vrr as u8) << 4
+            | (self.supports_neg_mvrr as u8) << 3
+            | (self.supports_fva as u8) << 2
+            | (self.supports_allm as u8) << 1
+            | self.supports_fapa_start_location as u8;
+        let vrr_min: u8 = encode_range(self.vrr_min_hz, 0, 0x3f, "vrr_min_hz")?;
+        let vrr_max: u16 = encode_range(self.vrr_max_hz, 0, 0x3ff, "vrr_max_hz")?;
+        let dsc = (self.qms_tfr_max as u8) << 5 | (self.qms_tfr_min as u8) << 4;
+
+        if self.dsc.is_some() || dsc != 0 || features != 0 || vrr_min != 0 || vrr_max != 0 {
+            data.push(features);
+            data.push(((vrr_max >> 8) << 6) as u8 | vrr_min);
+            data.push(vrr_max as u8);
+        }
+        if let Some(hdmi_dsc) = self.dsc {
+            let chunk_kbytes = (hdmi_dsc.max_total_chunk_bytes / 1024 - 1).max(0);
+            data.push(
+                1 << 7
+                    | (hdmi_dsc.supports_native_420 as u8) << 6
+                    | dsc
+                    | (hdmi_dsc.supports_all_bpc as u8) << 3
+                    | (hdmi_dsc.supports_12bpc as u8) << 1
+                    | hdmi_dsc.supports_10bpc as u8,
+            );
+            data.push(hdmi_dsc.max_frl_rate.encode() << 4 | hdmi_dsc.max_slices.encode());
+            data.push(encode_range(
+                chunk_kbytes,
+                0,
+                0x3f,
+                "max_total_chunk_bytes",
+            )?);
+        } else if dsc != 0 {
+            data.extend_from_slice(&[dsc, 0, 0]);
+        }
+
+        Ok(data)
+    }
+}
+
+/// Builder for a CTA-861 extension block.
+///
+/// Data blocks are stored in the order they were added, followed by the
+/// detailed timing definitions. Errors are reported by [`CtaBuilder::build`].
+#[derive(Debug, Clone)]
+pub struct CtaBuilder {
+    flags: Flags,
+    data_blocks: Vec<Result<Vec<u8>, EncodeError>>,
+    detailed_timing_defs: Vec<Result<[u8; 18], EncodeError>>,
+}
+
+impl Default for CtaBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CtaBuilder {
+    /// Create a new builder for a CTA-861 revision 3 extension block.
+    pub fn new() -> Self {
+        Self {
+            flags: Flags {
+                it_underscan: false,
+                basic_audio: false,
+                ycc444: false,
+                ycc422: false,
+                native_dtds: 0,
+            },
+            data_blocks: Vec::new(),
+            detailed_timing_defs: Vec::new(),
+        }
+    }
+
+    /// Set miscellaneous CTA flags.
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Add a detailed timing definition.
+    pub fn detailed_timing_def(mut self, timing: DetailedTimingDef) -> Self {
+        self.detailed_timing_defs.push(timing.encode());
+        self
+    }
+
+    /// Add a data block with the given tag and payload.
+    ///
+    /// The tag is the value defined in table 54 of the specification.
+    pub fn data_block(mut self, tag: u8, payload: &[u8]) -> Self {
+        self.data_blocks.push(encode_data_block(tag, None, payload));
+        self
+    }
+
+    /// Add an extended data block with the given extended tag and payload.
+    ///
+    /// The extended tag is the value defined in table 55 of the specification.
+    pub fn extended_data_block(mut self, ext_tag: u8, payload: &[u8]) -> Self {
+        self.data_blocks
+            .push(encode_data_block(TAG_EXTENDED, Some(ext_tag), payload));
+        self
+    }
+
+    fn push_block(
+        mut self,
+        tag: u8,
+        ext_tag: Option<u8>,
+        payload: Result<Vec<u8>, EncodeError>,
+    ) -> Self {
+        self.data_blocks
+            .push(payload.and_then(|payload| encode_data_block(tag, ext_tag, &payload)));
+        self
+    }
+
+    /// Add an audio data block.
+    pub fn audio(self, sads: &[Sad]) -> Self {
+        let payload = sads
+            .iter()
+            .map(Sad::encode)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|sads| sads.concat());
+        self.push_block(TAG_AUDIO, None, payload)
+    }
+
+    /// Add a video data block.
+    pub fn video(self, svds: &[Svd]) -> Self {
+        let payload = svds.iter().map(Svd::encode).collect();
+        self.push_block(TAG_VIDEO, None, payload)
+    }
+
+    /// Add a speaker allocation data block.
+    pub fn speaker_alloc(self, speakers: SpeakerAllocation) -> Self {
+        self.push_block(TAG_SPEAKER_ALLOC, None, Ok(speakers.encode().to_vec()))
+    }
+
+    /// Add a video capability data block.
+    pub fn video_cap(self, video_cap: VideoCapBlock) -> Self {
+        self.push_block(
+            TAG_EXTENDED,
+            Some(EXT_TAG_VIDEO_CAP),
+            Ok(vec![video_cap.encode()]),
+        )
+    }
+
+    /// Add a colorimetry data block.
+    pub fn colorimetry(self, colorimetry: ColorimetryBlock) -> Self {
+        self.push_block(
+            TAG_EXTENDED,
+            Some(EXT_TAG_COLORIMETRY),
+            Ok(colorimetry.encode().to_vec()),
+        )
+    }
+
+    /// Add an HDR static metadata data block.
+    ///
+    /// Luminance values are rounded to the nearest encodable value.
+    pub fn hdr_static_metadata(self, metadata: HdrStaticMetadataBlock) -> Self {
+        self.push_block(
+            TAG_EXTENDED,
+            Some(EXT_TAG_HDR_STATIC_METADATA),
+            metadata.encode(),
+        )
+    }
+
+    /// Add a YCbCr 4:2:0 video data block, listing formats which only
+    /// support YCbCr 4:2:0 sampling.
+    pub fn ycbcr420_video(self, svds: &[Svd]) -> Self {
+        let payload = svds.iter().map(Svd::encode).collect();
+        self.push_block(TAG_EXTENDED, Some(EXT_TAG_YCBCR420), payload)
+    }
+
+    /// Add a YCbCr 4:2:0 capability map data block.
+    ///
+    /// Entry `i` indicates whether the `i`-th SVD of the video data blocks
+    /// also supports YCbCr 4:2:0 sampling. An empty map indicates that all
+    /// SVDs do.
+    pub fn ycbcr420_cap_map(self, supported: &[bool]) -> Self {
+        let mut payload = vec![0u8; (supported.len() + 7) / 8];
+        for (i, _) in supported.iter().enumerate().filter(|(_, s)| **s) {
+            payload[i / 8] |= 1 << (i % 8);
+        }
+        self.push_block(TAG_EXTENDED, Some(EXT_TAG_YCBCR420_CAP_MAP), Ok(payload))
+    }
+
+    /// Add an HDMI vendor-specific data block, with the given HDMI VICs.
+    #[cfg(feature = "v0_3")]
+    pub fn vendor_hdmi(self, block: &VendorHdmiBlock, vics: &[u8]) -> Self {
+        let payload = block.encode(vics);
+        self.push_block(TAG_VENDOR, None, payload)
+    }
+
+    /// Add an HDMI Forum vendor-specific data block (HF-VSDB).
+    #[cfg(feature = "v0_3")]
+    pub fn vendor_hdmi_forum(self, scds: &HdmiScds) -> Self {
+        let payload = scds
+            .encode()
+            .map(|scds| [&OUI_HDMI_FORUM[..], &scds].concat());
+        self.push_block(TAG_VENDOR, None, payload)
+    }
+
+    /// Add an HDMI Forum sink capability data block (HF-SCDB).
+    #[cfg(feature = "v0_3")]
+    pub fn hdmi_sink_cap(self, scds: &HdmiScds) -> Self {
+        let payload = scds.encode().map(|scds| [&[0, 0][..], &scds].concat());
+        self.push_block(TAG_EXTENDED, Some(EXT_TAG_HDMI_SINK_CAP), payload)
+    }
+
+    /// Encode the extension block.
+    pub fn build(&self) -> Result<[u8; 128], EncodeError> {
+        let mut data = [0u8; 128];
+        data[0] = 0x02;
+        data[1] = 3;
+
+        let mut offset = 4;
+        for block in &self.data_blocks {
+            let block = block.as_ref().map_err(Clone::clone)?;
+            let end = offset + block.len();
+            if end > 127 {
+                return Err(EncodeError::BlockFull);
+            }
+            data[offset..end].copy_from_slice(block);
+            offset = end;
+        }
+
+        if offset > 4 || !self.detailed_timing_defs.is_empty() {
+            data[2] = offset as u8;
+        }
+        data[3] = (self.flags.it_underscan as u8) << 7
+            | (self.flags.basic_audio as u8) << 6
+            | (self.flags.ycc444 as u8) << 5
+            | (self.flags.ycc422 as u8) << 4
+            | encode_range::<u8>(self.flags.native_dtds, 0, 0xf, "native_dtds")?;
+
+        for timing in &self.detailed_timing_defs {
+            let timing = timing.as_ref().map_err(Clone::clone)?;
+            let end = offset + timing.len();
+            if end > 127 {
+                return Err(EncodeError::BlockFull);
+            }
+            data[offset..end].copy_from_slice(timing);
+            offset = end;
+        }
+
+        data[127] = checksum(&data[..127]);
+        Ok(data)
+    }
+}
+
+/// Prepend the data block header to a payload.
+fn encode_data_block(tag: u8, ext_tag: Option<u8>, payload: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let len = payload.len() + ext_tag.is_some() as usize;
+    if tag > 7 || len > 31 {
+        return Err(EncodeError::OutOfRange("data block"));
+    }
+
+    let mut data = Vec::with_capacity(len + 1);
+    data.push(tag << 5 | len as u8);
+    data.extend(ext_tag);
+    data.extend_from_slice(payload);
+    Ok(data)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{edid::tests::dtd_1080p60, mode::SyncPolarity};
+
+    pub(crate) fn sad(format: AudioFormat, max_channels: i32) -> Sad {
+        Sad {
+            format,
+            max_channels: Some(max_channels),
+            supported_sample_rates: None,
+            max_bitrate_kbs: None,
+            lpcm: None,
+            mpegh_3d: None,
+            mpeg_aac: None,
+            mpeg_surround: None,
+            mpeg_aac_le: None,
+            enhanced_ac3: None,
+            mat: None,
+            wma_pro: None,
+        }
+    }
+
+    pub(crate) fn svd(vic: u8, native: bool) -> Svd {
+        Svd {
+            vic,
+            #[cfg(feature = "v0_3")]
+            original_index: 0,
+            native,
+        }
+    }
+
+    /// 3840x2160 with the timing of HDMI VIC 1 (30 Hz), or VIC 2 (25 Hz) with a
+    /// longer horizontal front porch.
+    fn mode_2160p(h_front: i32) -> Mode {
+        Mode {
+            pixel_clock_hz: 297_000_000,
+            h_active: 3840,
+            h_front,
+            h_sync: 88,
+            h_back: 296,
+            v_active: 2160,
+            v_front: 8,
+            v_sync: 10,
+            v_back: 72,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    #[test]
+    fn fractional_rates() {
+        let fractional = |mode: Mode| Mode {
+            pixel_clock_hz: 296_703_297,
+            ..mode
+        };
+
+        let mode_30hz = mode_2160p(176);
+        assert_eq!(mode_30hz.refresh_rate_mhz(), 30_000);
+        assert!(matches_format(&mode_30hz, mode_30hz));
+        assert!(matches_format(&fractional(mode_30hz), mode_30hz));
+
+        let mode_25hz = mode_2160p(1056);
+        assert_eq!(mode_25hz.refresh_rate_mhz(), 25_000);
+        assert!(matches_format(&mode_25hz, mode_25hz));
+        assert!(!matches_format(&fractional(mode_25hz), mode_25hz));
+    }
+
+    #[test]
+    fn ycbcr420_cap_map() {
+        // Two video data blocks, the third SVD (index 2) being invalid and
+        // dropped. The capability map marks indices 1 and 3.
+        let svd = |vic, _original_index| Svd {
+            vic,
+            #[cfg(feature = "v0_3")]
+            original_index: _original_index,
+            native: false,
+        };
+        let svds = [svd(16, 0), svd(4, 1), svd(97, 3), svd(96, 4)];
+        let support = ycbcr420_cap_map_support(&svds, |index| matches!(index, 1 | 3));
+
+        #[cfg(feature = "v0_3")]
+        assert_eq!(support, [false, true, true, false]);
+        #[cfg(not(feature = "v0_3"))]
+        assert_eq!(support, [false; 4]);
+    }
+
+    #[cfg(any(feature = "v0_2", feature = "v0_3"))]
+    fn speaker_allocation(speakers: &[&str]) -> SpeakerAllocation {
+        let has = |name| speakers.contains(&name);
+        SpeakerAllocation {
+            flw_frw: has("flw_frw"),
+            flc_frc: has("flc_frc"),
+            bc: has("bc"),
+            bl_br: has("bl_br"),
+            fc: has("fc"),
+            lfe1: has("lfe1"),
+            fl_fr: has("fl_fr"),
+            tpsil_tpsir: has("tpsil_tpsir"),
+            sil_sir: has("sil_sir"),
+            tpbc: has("tpbc"),
+            lfe2: has("lfe2"),
+            ls_rs: has("ls_rs"),
+            tpfc: has("tpfc"),
+            tpc: has("tpc"),
+            tpfl_tpfr: has("tpfl_tpfr"),
+            btfl_btfr: has("btfl_btfr"),
+            btfc: has("btfc"),
+            tpbl_tpbr: has("tpbl_tpbr"),
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "v0_2", feature = "v0_3"))]
+    fn channel_allocations() {
+        use SpeakerPlacement::*;
+
+        let channels = |ca| ChannelAllocation::from_ca(ca).unwrap().channels();
+        assert_eq!(
+            channels(0x0b),
+            [
+                Some(FL),
+                Some(FR),
+                Some(LFE1),
+                Some(FC),
+                Some(BL),
+                Some(BR),
+                None,
+                None
+            ]
+        );
+        assert_eq!(
+            channels(0x13),
+            [
+                Some(FL),
+                Some(FR),
+                Some(LFE1),
+                Some(FC),
+                Some(BL),
+                Some(BR),
+                Some(LS),
+                Some(RS)
+            ]
+        );
+        assert_eq!(
+            channels(0x31),
+            [
+                Some(FL),
+                Some(FR),
+                Some(LFE1),
+                Some(FC),
+                Some(BL),
+                Some(BR),
+                Some(FLW),
+                Some(FRW)
+            ]
+        );
+        assert_eq!(ChannelAllocation::from_ca(0x32), None);
+        assert_eq!(
+            ChannelAllocation::from_positions(&[FR, FL, FC, LFE1, BR, BL]).map(|ca| ca.ca()),
+            Some(0x0b)
+        );
+
+        let surround_5_1 = speaker_allocation(&["fl_fr", "lfe1", "fc", "bl_br"]);
+        let surround_7_1 = speaker_allocation(&["fl_fr", "lfe1", "fc", "bl_br", "ls_rs"]);
+        assert_eq!(surround_5_1.channel_allocation(6).ca(), 0x0b);
+        assert_eq!(surround_5_1.channel_allocation(8).ca(), 0x0b);
+        assert_eq!(surround_7_1.channel_allocation(6).ca(), 0x0b);
+        assert_eq!(surround_7_1.channel_allocation(8).ca(), 0x13);
+        assert_eq!(surround_7_1.channel_allocation(8).channel_count(), 8);
+        assert_eq!(speaker_allocation(&[]).channel_allocation(8).ca(), 0x00);
+    }
+
+    #[test]
+    #[cfg(any(feature = "v0_2", feature = "v0_3"))]
+    fn speaker_channel_positions() {
+        use SpeakerPlacement::*;
+
+        let locations = [
+            (0, true, FL),
+            (1, true, FR),
+            (3, true, LFE1),
+            (2, false, FC),
+        ];
+        assert_eq!(
+            channel_positions(locations.into_iter()),
+            [Some(FL), Some(FR), None, Some(LFE1)]
+        );
+        assert_eq!(channel_positions([(-1, true, FC)].into_iter()), []);
+    }
+
+    /// Decode the channel count of an L-PCM 3D audio SAD, the way
+    /// libdisplay-info does.
+    fn lpcm_3d_channels(data: [u8; 3]) -> i32 {
+        let channels = (data[0] & 0x80) >> 4 | (data[1] & 0x80) >> 3 | data[0] & 0x7;
+        channels as i32 + 1
+    }
+
+    #[test]
+    fn lpcm_3d_channels_round_trip() {
+        for max_channels in [1, 8, 9, 16, 17, 32] {
+            let data = sad(AudioFormat::LPCM_3D, max_channels).encode().unwrap();
+            assert_eq!(data[0] >> 3 & 0xf, 15);
+            assert_eq!(data[2] >> 3, 13);
+            assert_eq!(lpcm_3d_channels(data), max_channels);
+        }
+
+        assert!(sad(AudioFormat::LPCM_3D, 33).encode().is_err());
+        assert!(sad(AudioFormat::LPCM, 9).encode().is_err());
+    }
+
+    #[test]
+    fn extension_block() {
+        let block = CtaBuilder::new()
+            .flags(Flags {
+                it_underscan: false,
+                basic_audio: true,
+                ycc444: true,
+                ycc422: true,
+                native_dtds: 1,
+            })
+            .video(&[svd(16, true), svd(4, false)])
+            .audio(&[sad(AudioFormat::LPCM, 2)])
+            .ycbcr420_cap_map(&[false, true])
+            .detailed_timing_def(dtd_1080p60())
+            .build()
+            .unwrap();
+
+        assert_eq!(block[..4], [0x02, 3, 14, 0x71]);
+        assert_eq!(block[4..7], [0x42, 0x90, 0x04]);
+        assert_eq!(block[7..11], [0x23, 0x09, 0x00, 0x00]);
+        assert_eq!(block[11..14], [0xe2, 0x0f, 0x02]);
+        assert_eq!(block[14..32], dtd_1080p60().encode().unwrap());
+        assert!(block[32..127].iter().all(|&b| b == 0));
+        assert_eq!(block.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)), 0);
+    }
+
+    #[test]
+    fn encode_errors() {
+        assert!(matches!(
+            CtaBuilder::new().video(&[svd(65, true)]).build(),
+            Err(EncodeError::OutOfRange("vic"))
+        ));
+        assert!(matches!(
+            CtaBuilder::new().data_block(2, &[0; 32]).build(),
+            Err(EncodeError::OutOfRange("data block"))
+        ));
+
+        let builder = (0..5).fold(CtaBuilder::new(), |builder, _| {
+            builder.data_block(7, &[0; 30])
+        });
+        assert!(matches!(builder.build(), Err(EncodeError::BlockFull)));
+    }
+}
@@ -5,6 +5,9 @@ use libdisplay_info_derive::FFIFrom;
 
 use crate::ffi;
 
+/// Minimum vertical blanking time in µs of the reduced blanking timings.
+pub const RB_MIN_V_BLANK: f64 = 460.0;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FFIFrom)]
 #[ffi(ffi::cvt::di_cvt_reduced_blanking_version)]
 #[repr(u32)]
@@ -31,6 +34,30 @@ pub struct Options {
     pub margins_rqd: bool,
 }
 
+impl Options {
+    /// Options for a progressive timing without margins, using the minimum
+    /// vertical blanking time and no additional horizontal blanking.
+    pub fn new(
+        red_blank_ver: ReducedBlankingVersion,
+        h_pixels: i32,
+        v_lines: i32,
+        ip_freq_rqd: f64,
+    ) -> Self {
+        Options {
+            red_blank_ver,
+            h_pixels,
+            v_lines,
+            ip_freq_rqd,
+            video_opt: false,
+            vblank: RB_MIN_V_BLANK,
+            additional_hblank: 0,
+            early_vsync_rqd: false,
+            int_rqd: false,
+            margins_rqd: false,
+        }
+    }
+}
+
 /// Output parameters, defined in table 3-4.
 #[derive(Debug, Copy, Clone, FFIFrom)]
 #[ffi(ffi::cvt::di_cvt_timing)]
@@ -6,7 +6,10 @@ use std::marker::PhantomData;
 
 use libdisplay_info_derive::FFIFrom;
 
-use crate::{edid::ExtensionRef, ffi, FFIIter};
+use crate::{
+    edid::{checksum, encode_range, EncodeError, ExtensionRef},
+    ffi, FFIIter,
+};
 
 pub struct DisplayId<'ext> {
     display_id: *const ffi::displayid::di_displayid,
@@ -281,6 +284,48 @@ pub struct TypeIIITiming {
     pub refresh_rate_hz: i32,
 }
 
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+impl TypeIIITiming {
+    /// Get the number of vertical active lines, derived from the horizontal
+    /// active pixels and the aspect ratio.
+    ///
+    /// Returns `None` if the aspect ratio is undefined.
+    pub fn vert_active(&self) -> Option<i32> {
+        let (width, height) = match self.aspect_ratio {
+            TimingAspectRatio::_1_1 => (1, 1),
+            TimingAspectRatio::_5_4 => (5, 4),
+            TimingAspectRatio::_4_3 => (4, 3),
+            TimingAspectRatio::_15_9 => (15, 9),
+            TimingAspectRatio::_16_9 => (16, 9),
+            TimingAspectRatio::_16_10 => (16, 10),
+            TimingAspectRatio::_64_27 => (64, 27),
+            TimingAspectRatio::_256_135 => (256, 135),
+            TimingAspectRatio::Undefined => return None,
+        };
+        Some(self.horiz_active * height / width)
+    }
+
+    /// Compute the timing via the CVT formula.
+    ///
+    /// DisplayID 1.3 predates CVT 2.0, the reduced blanking algorithm is the
+    /// first version. Returns `None` if the aspect ratio is undefined.
+    pub fn timing(&self) -> Option<crate::cvt::Timing> {
+        let red_blank_ver = match self.algo {
+            TyoeIIITimingAlgo::CvtStandardBlanking => crate::cvt::ReducedBlankingVersion::None,
+            TyoeIIITimingAlgo::CvtReducedBlacking => crate::cvt::ReducedBlankingVersion::V1,
+        };
+        Some(crate::cvt::Timing::compute(crate::cvt::Options {
+            int_rqd: self.interlaced,
+            ..crate::cvt::Options::new(
+                red_blank_ver,
+                self.horiz_active,
+                self.vert_active()?,
+                self.refresh_rate_hz as f64,
+            )
+        }))
+    }
+}
+
 /// Behavior when more than 1 tile and less than total number of tiles are driven
 /// by the source.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FFIFrom)]
@@ -343,3 +388,526 @@ pub struct TiledTopo {
     pub product_code: u16,
     pub serial_number: u32,
 }
+
+/// Product identification, defined in section 4.1.
+///
+/// This is only used when encoding DisplayID sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductId {
+    /// IEEE OUI of the manufacturer
+    pub vendor_oui: [u8; 3],
+    pub product_code: u16,
+    pub serial_number: u32,
+    /// Week of manufacture, 0 if unspecified
+    pub manufacture_week: i32,
+    pub manufacture_year: i32,
+    /// Model year, replaces the week and year of manufacture if set
+    pub model_year: Option<i32>,
+    /// ASCII product name, up to 235 characters
+    pub product_name: String,
+}
+
+impl ProductId {
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        if !self.product_name.is_ascii() || self.product_name.len() > 235 {
+            return Err(EncodeError::InvalidString(self.product_name.clone()));
+        }
+
+        let mut data = self.vendor_oui.to_vec();
+        data.extend_from_slice(&self.product_code.to_le_bytes());
+        data.extend_from_slice(&self.serial_number.to_le_bytes());
+        if let Some(model_year) = self.model_year {
+            data.push(0xff);
+            data.push(encode_range(model_year - 2000, 0, 0xff, "model_year")?);
+        } else {
+            data.push(encode_range(
+                self.manufacture_week,
+                0,
+                54,
+                "manufacture_week",
+            )?);
+            data.push(encode_range(
+                self.manufacture_year - 2000,
+                0,
+                0xff,
+                "manufacture_year",
+            )?);
+        }
+        data.push(self.product_name.len() as u8);
+        data.extend_from_slice(self.product_name.as_bytes());
+        Ok(data)
+    }
+}
+
+impl ProductType {
+    fn encode(&self) -> u8 {
+        match self {
+            ProductType::Extension => 0,
+            ProductType::Test => 1,
+            ProductType::DisplayPanel => 2,
+            ProductType::StandaloneDisplay => 3,
+            ProductType::TvReceiver => 4,
+            ProductType::Repeater => 5,
+            ProductType::DirectDrive => 6,
+        }
+    }
+}
+
+impl DisplayParams {
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let image_size = |mm: f32, field| {
+            encode_range::<u16>((mm as f64 * 10.0).round() as i64, 0, 0xffff, field)
+        };
+        let bits_per_color = |bits: i32, field| encode_range::<u8>(bits - 1, 0, 0xf, field);
+
+        let mut data = Vec::with_capacity(12);
+        data.extend_from_slice(&image_size(self.horiz_image_mm, "horiz_image_mm")?.to_le_bytes());
+        data.extend_from_slice(&image_size(self.vert_image_mm, "vert_image_mm")?.to_le_bytes());
+        data.extend_from_slice(
+            &encode_range::<u16>(self.horiz_pixels, 0, 0xffff, "horiz_pixels")?.to_le_bytes(),
+        );
+        data.extend_from_slice(
+            &encode_range::<u16>(self.vert_pixels, 0, 0xffff, "vert_pixels")?.to_le_bytes(),
+        );
+        data.push(self.features.map_or(0, |features| {
+            (features.audio as u8) << 7
+                | (features.separate_audio_inputs as u8) << 6
+                | (features.audio_input_override as u8) << 5
+                | (features.power_management as u8) << 4
+                | (features.fixed_timing as u8) << 3
+                | (features.fixed_pixel_format as u8) << 2
+                | (features.ai as u8) << 1
+                | features.deinterlacing as u8
+        }));
+        data.push(match self.gamma {
+            Some(gamma) => encode_range(
+                (gamma as f64 * 100.0).round() as i64 - 100,
+                0,
+                0xfe,
+                "gamma",
+            )?,
+            None => 0xff,
+        });
+        data.push(encode_range(
+            (self.aspect_ratio as f64 * 100.0).round() as i64 - 100,
+            0,
+            0xff,
+            "aspect_ratio",
+        )?);
+        data.push(
+            bits_per_color(self.bits_per_color_overall, "bits_per_color_overall")? << 4
+                | bits_per_color(self.bits_per_color_native, "bits_per_color_native")?,
+        );
+        Ok(data)
+    }
+}
+
+impl TimingAspectRatio {
+    pub(crate) fn encode(&self) -> u8 {
+        match self {
+            TimingAspectRatio::_1_1 => 0,
+            TimingAspectRatio::_5_4 => 1,
+            TimingAspectRatio::_4_3 => 2,
+            TimingAspectRatio::_15_9 => 3,
+            TimingAspectRatio::_16_9 => 4,
+            TimingAspectRatio::_16_10 => 5,
+            TimingAspectRatio::_64_27 => 6,
+            TimingAspectRatio::_256_135 => 7,
+            TimingAspectRatio::Undefined => 8,
+        }
+    }
+}
+
+impl TypeIIIVIITiming {
+    /// Encode a 20 byte timing descriptor, with the pixel clock in units of
+    /// `clock_unit_khz`.
+    pub(crate) fn encode(&self, clock_unit_khz: f64) -> Result<[u8; 20], EncodeError> {
+        let stereo_3d = match self.stereo_3d {
+            TypeIIIVIITimingStereo3d::Never => 0,
+            TypeIIIVIITimingStereo3d::Always => 1,
+            TypeIIIVIITimingStereo3d::User => 2,
+        };
+        let polarity = |polarity| match polarity {
+            TypeIIIVIITimingSyncPolarity::Negative => 0,
+            TypeIIIVIITimingSyncPolarity::Positive => 1 << 15,
+        };
+        let field = |value: i32, max, field| encode_range::<u16>(value - 1, 0, max, field);
+
+        let pixel_clock: u32 = encode_range(
+            (self.pixel_clock_mhz * 1000.0 / clock_unit_khz).round() as i64 - 1,
+            0,
+            0xff_ffff,
+            "pixel_clock_mhz",
+        )?;
+        let values = [
+            field(self.horiz_active, 0xffff, "horiz_active")?,
+            field(self.horiz_blank, 0xffff, "horiz_blank")?,
+            field(self.horiz_offset, 0x7fff, "horiz_offset")? | polarity(self.horiz_sync_polarity),
+            field(self.horiz_sync_width, 0xffff, "horiz_sync_width")?,
+            field(self.vert_active, 0xffff, "vert_active")?,
+            field(self.vert_blank, 0xffff, "vert_blank")?,
+            field(self.vert_offset, 0x7fff, "vert_offset")? | polarity(self.vert_sync_polarity),
+            field(self.vert_sync_width, 0xffff, "vert_sync_width")?,
+        ];
+
+        let mut data = [0u8; 20];
+        data[0..3].copy_from_slice(&pixel_clock.to_le_bytes()[..3]);
+        data[3] = (self.preferred as u8) << 7
+            | stereo_3d << 5
+            | (self.interlaced as u8) << 4
+            | self.aspect_ratio.encode();
+        for (i, value) in values.iter().enumerate() {
+            data[4 + 2 * i..6 + 2 * i].copy_from_slice(&value.to_le_bytes());
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(any(feature = "v0_2", feature = "v0_3"))]
+impl TypeIIITiming {
+    fn encode(&self) -> Result<[u8; 3], EncodeError> {
+        let algo = match self.algo {
+            TyoeIIITimingAlgo::CvtStandardBlanking => 0,
+            TyoeIIITimingAlgo::CvtReducedBlacking => 1,
+        };
+        if self.horiz_active % 8 != 0 {
+            return Err(EncodeError::OutOfRange("horiz_active"));
+        }
+
+        Ok([
+            (self.preferred as u8) << 7 | algo << 4 | self.aspect_ratio.encode(),
+            encode_range(self.horiz_active / 8 - 1, 0, 0xff, "horiz_active")?,
+            (self.interlaced as u8) << 7
+                | encode_range::<u8>(self.refresh_rate_hz - 1, 0, 0x7f, "refresh_rate_hz")?,
+        ])
+    }
+}
+
+impl TiledTopo {
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let total_horiz =
+            encode_range::<u8>(self.total_horiz_tiles - 1, 0, 0x3f, "total_horiz_tiles")?;
+        let total_vert =
+            encode_range::<u8>(self.total_vert_tiles - 1, 0, 0x3f, "total_vert_tiles")?;
+        let horiz_location =
+            encode_range::<u8>(self.horiz_tile_location, 0, 0x3f, "horiz_tile_location")?;
+        let vert_location =
+            encode_range::<u8>(self.vert_tile_location, 0, 0x3f, "vert_tile_location")?;
+
+        let mut data = vec![0u8; 22];
+        if let Some(caps) = self.caps {
+            let missing_recv = match caps.missing_recv_behavior {
+                TiledTopoMissingRecvBehavior::Undef => 0,
+                TiledTopoMissingRecvBehavior::TileOnly => 1,
+            };
+            let single_recv = match caps.single_recv_behavior {
+                TiledTopoSingleRecvBehavior::Undef => 0,
+                TiledTopoSingleRecvBehavior::TileOnly => 1,
+                TiledTopoSingleRecvBehavior::Scaled => 2,
+                TiledTopoSingleRecvBehavior::Cloned => 3,
+            };
+            data[0] = (caps.single_enclosure as u8) << 7 | missing_recv << 3 | single_recv;
+        }
+        data[1] = (total_horiz & 0xf) << 4 | total_vert & 0xf;
+        data[2] = (horiz_location & 0xf) << 4 | vert_location & 0xf;
+        data[3] = (total_horiz >> 4) << 6
+            | (total_vert >> 4) << 4
+            | (horiz_location >> 4) << 2
+            | vert_location >> 4;
+        data[4..6].copy_from_slice(
+            &encode_range::<u16>(self.horiz_tile_pixels - 1, 0, 0xffff, "horiz_tile_pixels")?
+                .to_le_bytes(),
+        );
+        data[6..8].copy_from_slice(
+            &encode_range::<u16>(self.vert_tile_lines - 1, 0, 0xffff, "vert_tile_lines")?
+                .to_le_bytes(),
+        );
+
+        if let Some(bezel) = self.bezel {
+            // Bezel sizes are stored in units of a tenth of the pixel
+            // multiplier, pick the smallest multiplier which fits them all.
+            let bezels = [bezel.top_px, bezel.bottom_px, bezel.right_px, bezel.left_px];
+            let max = bezels
+                .iter()
+                .fold(0.0f64, |max, px| max.max(*px as f64 * 10.0));
+            let multiplier = (max / 255.0).ceil().max(1.0);
+            data[0] |= 1 << 6;
+            data[8] = encode_range((multiplier) as i64, 1, 0xff, "bezel")?;
+            for (i, px) in bezels.iter().enumerate() {
+                data[9 + i] = encode_range(
+                    (*px as f64 * 10.0 / multiplier).round() as i64,
+                    0,
+                    0xff,
+                    "bezel",
+                )?;
+            }
+        }
+
+        for (i, c) in self.vendor_id.iter().enumerate() {
+            if !c.is_ascii() {
+                return Err(EncodeError::OutOfRange("vendor_id"));
+            }
+            data[13 + i] = *c as u8;
+        }
+        data[16..18].copy_from_slice(&self.product_code.to_le_bytes());
+        data[18..22].copy_from_slice(&self.serial_number.to_le_bytes());
+        Ok(data)
+    }
+}
+
+/// Prepend the data block header to a payload.
+pub(crate) fn encode_data_block(
+    tag: u8,
+    revision: u8,
+    payload: &[u8],
+) -> Result<Vec<u8>, EncodeError> {
+    if payload.len() > 248 {
+        return Err(EncodeError::OutOfRange("data block"));
+    }
+
+    let mut data = Vec::with_capacity(payload.len() + 3);
+    data.extend_from_slice(&[tag, revision, payload.len() as u8]);
+    data.extend_from_slice(payload);
+    Ok(data)
+}
+
+/// Assemble a DisplayID section from its version byte, product type byte and
+/// data blocks.
+pub(crate) fn encode_section(
+    version: u8,
+    product_type: u8,
+    data_blocks: &[Result<Vec<u8>, EncodeError>],
+    max_payload: usize,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut data = vec![version, 0, product_type, 0];
+    for block in data_blocks {
+        data.extend_from_slice(block.as_ref().map_err(Clone::clone)?);
+    }
+
+    let payload = data.len() - 4;
+    if payload > max_payload {
+        return Err(EncodeError::BlockFull);
+    }
+    data[1] = payload as u8;
+    data.push(checksum(&data));
+    Ok(data)
+}
+
+/// Maximum payload of a DisplayID section stored in an EDID extension block.
+pub(crate) const EXTENSION_MAX_PAYLOAD: usize = 121;
+
+/// Maximum payload of a standalone DisplayID section.
+pub(crate) const SECTION_MAX_PAYLOAD: usize = 251;
+
+/// Wrap a DisplayID section in an EDID extension block.
+pub(crate) fn encode_extension(section: &[u8]) -> [u8; 128] {
+    let mut data = [0u8; 128];
+    data[0] = 0x70;
+    data[1..1 + section.len()].copy_from_slice(section);
+    data[127] = checksum(&data[..127]);
+    data
+}
+
+/// Builder for a DisplayID 1.3 section.
+///
+/// Data blocks are stored in the order they were added. Errors are reported
+/// when building the section.
+#[derive(Debug, Clone)]
+pub struct DisplayIdBuilder {
+    product_type: ProductType,
+    data_blocks: Vec<Result<Vec<u8>, EncodeError>>,
+}
+
+impl DisplayIdBuilder {
+    /// Create a new builder for a section with the given product type.
+    pub fn new(product_type: ProductType) -> Self {
+        Self {
+            product_type,
+            data_blocks: Vec::new(),
+        }
+    }
+
+    /// Set the product type.
+    pub fn product_type(mut self, product_type: ProductType) -> Self {
+        self.product_type = product_type;
+        self
+    }
+
+    /// Add a data block with the given tag, revision and payload.
+    pub fn data_block(mut self, tag: u8, revision: u8, payload: &[u8]) -> Self {
+        self.data_blocks
+            .push(encode_data_block(tag, revision, payload));
+        self
+    }
+
+    fn push_block(mut self, tag: u8, payload: Result<Vec<u8>, EncodeError>) -> Self {
+        self.data_blocks
+            .push(payload.and_then(|payload| encode_data_block(tag, 0, &payload)));
+        self
+    }
+
+    /// Add a product identification data block.
+    pub fn product_id(self, product_id: &ProductId) -> Self {
+        self.push_block(0x00, product_id.encode())
+    }
+
+    /// Add a display parameters data block.
+    pub fn display_params(self, params: DisplayParams) -> Self {
+        self.push_block(0x01, params.encode())
+    }
+
+    /// Add a type I timing data block.
+    pub fn type_i_timings(self, timings: &[TypeIIIVIITiming]) -> Self {
+        let payload = timings
+            .iter()
+            .map(|timing| timing.encode(10.0))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|timings| timings.concat());
+        self.push_block(0x03, payload)
+    }
+
+    /// Add a type III timing data block.
+    #[cfg(any(feature = "v0_2", feature = "v0_3"))]
+    pub fn type_iii_timings(self, timings: &[TypeIIITiming]) -> Self {
+        let payload = timings
+            .iter()
+            .map(TypeIIITiming::encode)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|timings| timings.concat());
+        self.push_block(0x05, payload)
+    }
+
+    /// Add a tiled display topology data block.
+    pub fn tiled_topo(self, tiled_topo: TiledTopo) -> Self {
+        self.push_block(0x12, tiled_topo.encode())
+    }
+
+    /// Encode a standalone DisplayID section, including its checksum.
+    pub fn build_section(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_section(
+            0x13,
+            self.product_type.encode(),
+            &self.data_blocks,
+            SECTION_MAX_PAYLOAD,
+        )
+    }
+
+    /// Encode an EDID extension block holding the DisplayID section.
+    pub fn build_extension(&self) -> Result<[u8; 128], EncodeError> {
+        let section = encode_section(
+            0x13,
+            self.product_type.encode(),
+            &self.data_blocks,
+            EXTENSION_MAX_PAYLOAD,
+        )?;
+        Ok(encode_extension(&section))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Type I timing of 1920x1080@60, CTA-861 VIC 16.
+    pub(crate) fn timing_1080p60() -> TypeIIIVIITiming {
+        TypeIIIVIITiming {
+            pixel_clock_mhz: 148.5,
+            preferred: true,
+            stereo_3d: TypeIIIVIITimingStereo3d::Never,
+            interlaced: false,
+            aspect_ratio: TimingAspectRatio::_16_9,
+            horiz_active: 1920,
+            vert_active: 1080,
+            horiz_blank: 280,
+            vert_blank: 45,
+            horiz_offset: 88,
+            vert_offset: 4,
+            horiz_sync_width: 44,
+            vert_sync_width: 5,
+            horiz_sync_polarity: TypeIIIVIITimingSyncPolarity::Positive,
+            vert_sync_polarity: TypeIIIVIITimingSyncPolarity::Positive,
+        }
+    }
+
+    pub(crate) fn tiled_topo() -> TiledTopo {
+        TiledTopo {
+            caps: Some(TiledTopoCaps {
+                single_enclosure: true,
+                missing_recv_behavior: TiledTopoMissingRecvBehavior::TileOnly,
+                single_recv_behavior: TiledTopoSingleRecvBehavior::Scaled,
+            }),
+            total_horiz_tiles: 2,
+            total_vert_tiles: 1,
+            horiz_tile_location: 1,
+            vert_tile_location: 0,
+            horiz_tile_pixels: 1920,
+            vert_tile_lines: 2160,
+            bezel: Some(TiledTopoBezel {
+                top_px: 2.5,
+                bottom_px: 2.5,
+                right_px: 0.0,
+                left_px: 0.0,
+            }),
+            vendor_id: ['L', 'D', 'I'],
+            product_code: 0x1234,
+            serial_number: 1,
+        }
+    }
+
+    #[test]
+    fn type_i_timing() {
+        assert_eq!(
+            timing_1080p60().encode(10.0).unwrap(),
+            [
+                0x01, 0x3a, 0x00, 0x84, 0x7f, 0x07, 0x17, 0x01, 0x57, 0x80, 0x2b, 0x00, 0x37, 0x04,
+                0x2c, 0x00, 0x03, 0x80, 0x04, 0x00
+            ]
+        );
+    }
+
+    #[test]
+    fn tiled_topology() {
+        assert_eq!(
+            tiled_topo().encode().unwrap(),
+            [
+                0xca, 0x10, 0x10, 0x00, 0x7f, 0x07, 0x6f, 0x08, 0x01, 25, 25, 0, 0, b'L', b'D',
+                b'I', 0x34, 0x12, 0x01, 0x00, 0x00, 0x00
+            ]
+        );
+    }
+
+    #[test]
+    fn section_and_extension() {
+        let builder = DisplayIdBuilder::new(ProductType::StandaloneDisplay)
+            .type_i_timings(&[timing_1080p60()])
+            .tiled_topo(tiled_topo());
+
+        let section = builder.build_section().unwrap();
+        assert_eq!(section.len(), 5 + 23 + 25);
+        assert_eq!(section[..4], [0x13, 48, 3, 0]);
+        assert_eq!(section[4..7], [0x03, 0, 20]);
+        assert_eq!(section[27..30], [0x12, 0, 22]);
+        assert_eq!(checksum(&section), 0);
+
+        let extension = builder.build_extension().unwrap();
+        assert_eq!(extension[0], 0x70);
+        assert_eq!(extension[1..1 + section.len()], section[..]);
+        assert_eq!(checksum(&extension), 0);
+    }
+
+    #[test]
+    fn encode_errors() {
+        let builder =
+            DisplayIdBuilder::new(ProductType::Extension).type_i_timings(&[timing_1080p60(); 6]);
+        assert!(builder.build_section().is_ok());
+        assert_eq!(builder.build_extension(), Err(EncodeError::BlockFull));
+
+        let mut topo = tiled_topo();
+        topo.total_horiz_tiles = 65;
+        let builder = DisplayIdBuilder::new(ProductType::Extension).tiled_topo(topo);
+        assert_eq!(
+            builder.build_section(),
+            Err(EncodeError::OutOfRange("total_horiz_tiles"))
+        );
+    }
+}
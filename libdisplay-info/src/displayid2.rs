@@ -6,7 +6,14 @@ use std::marker::PhantomData;
 
 use libdisplay_info_derive::FFIFrom;
 
-use crate::{edid::ExtensionRef, ffi};
+use crate::{
+    displayid::{
+        encode_data_block, encode_extension, encode_section, ProductId, TiledTopo,
+        TypeIIIVIITiming, EXTENSION_MAX_PAYLOAD, SECTION_MAX_PAYLOAD,
+    },
+    edid::{EncodeError, ExtensionRef},
+    ffi,
+};
 
 pub struct DisplayId2<'ext> {
     display_id: *const ffi::displayid2::di_displayid2,
@@ -59,3 +66,130 @@ pub enum PrimaryUseCase {
     HMDVR = ffi::displayid2::di_displayid2_product_primary_use_case_DI_DISPLAYID2_PRODUCT_PRIMARY_USE_CASE_HMD_VR,
     HMDAR = ffi::displayid2::di_displayid2_product_primary_use_case_DI_DISPLAYID2_PRODUCT_PRIMARY_USE_CASE_HMD_AR,
 }
+
+impl PrimaryUseCase {
+    fn encode(&self) -> u8 {
+        match self {
+            PrimaryUseCase::Extension => 0,
+            PrimaryUseCase::Test => 1,
+            PrimaryUseCase::Generic => 2,
+            PrimaryUseCase::TV => 3,
+            PrimaryUseCase::DesktopProductivity => 4,
+            PrimaryUseCase::DesktopGaming => 5,
+            PrimaryUseCase::Presentation => 6,
+            PrimaryUseCase::HMDVR => 7,
+            PrimaryUseCase::HMDAR => 8,
+        }
+    }
+}
+
+/// Builder for a DisplayID 2.0 section.
+///
+/// Data blocks are stored in the order they were added. Errors are reported
+/// when building the section.
+#[derive(Debug, Clone)]
+pub struct DisplayId2Builder {
+    primary_use_case: PrimaryUseCase,
+    data_blocks: Vec<Result<Vec<u8>, EncodeError>>,
+}
+
+impl DisplayId2Builder {
+    /// Create a new builder for a section with the given product primary use
+    /// case.
+    pub fn new(primary_use_case: PrimaryUseCase) -> Self {
+        Self {
+            primary_use_case,
+            data_blocks: Vec::new(),
+        }
+    }
+
+    /// Set the product primary use case.
+    pub fn product_primary_use_case(mut self, primary_use_case: PrimaryUseCase) -> Self {
+        self.primary_use_case = primary_use_case;
+        self
+    }
+
+    /// Add a data block with the given tag, revision and payload.
+    pub fn data_block(mut self, tag: u8, revision: u8, payload: &[u8]) -> Self {
+        self.data_blocks
+            .push(encode_data_block(tag, revision, payload));
+        self
+    }
+
+    fn push_block(mut self, tag: u8, payload: Result<Vec<u8>, EncodeError>) -> Self {
+        self.data_blocks
+            .push(payload.and_then(|payload| encode_data_block(tag, 0, &payload)));
+        self
+    }
+
+    /// Add a product identification data block.
+    pub fn product_id(self, product_id: &ProductId) -> Self {
+        self.push_block(0x20, product_id.encode())
+    }
+
+    /// Add a type VII timing data block.
+    ///
+    /// Type VII timings share the layout of type I timings, with the pixel
+    /// clock stored in units of 1 kHz.
+    pub fn type_vii_timings(self, timings: &[TypeIIIVIITiming]) -> Self {
+        let payload = timings
+            .iter()
+            .map(|timing| timing.encode(1.0))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|timings| timings.concat());
+        self.push_block(0x22, payload)
+    }
+
+    /// Add a tiled display topology data block.
+    pub fn tiled_topo(self, tiled_topo: TiledTopo) -> Self {
+        self.push_block(0x28, tiled_topo.encode())
+    }
+
+    /// Encode a standalone DisplayID section, including its checksum.
+    pub fn build_section(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_section(
+            0x20,
+            self.primary_use_case.encode(),
+            &self.data_blocks,
+            SECTION_MAX_PAYLOAD,
+        )
+    }
+
+    /// Encode an EDID extension block holding the DisplayID section.
+    pub fn build_extension(&self) -> Result<[u8; 128], EncodeError> {
+        let section = encode_section(
+            0x20,
+            self.primary_use_case.encode(),
+            &self.data_blocks,
+            EXTENSION_MAX_PAYLOAD,
+        )?;
+        Ok(encode_extension(&section))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::displayid::tests::{tiled_topo, timing_1080p60};
+    use crate::edid::checksum;
+
+    #[test]
+    fn section_and_extension() {
+        let builder = DisplayId2Builder::new(PrimaryUseCase::DesktopGaming)
+            .type_vii_timings(&[timing_1080p60()])
+            .tiled_topo(tiled_topo());
+
+        let section = builder.build_section().unwrap();
+        assert_eq!(section[..4], [0x20, 48, 5, 0]);
+        assert_eq!(section[4..7], [0x22, 0, 20]);
+        // Type VII timings store the pixel clock in units of 1 kHz.
+        assert_eq!(section[7..10], [0x13, 0x44, 0x02]);
+        assert_eq!(section[27..30], [0x28, 0, 22]);
+        assert_eq!(checksum(&section), 0);
+
+        let extension = builder.build_extension().unwrap();
+        assert_eq!(extension[0], 0x70);
+        assert_eq!(extension[1..1 + section.len()], section[..]);
+        assert_eq!(checksum(&extension), 0);
+    }
+}
@@ -5,7 +5,7 @@ use libdisplay_info_derive::FFIFrom;
 
 use crate::ffi;
 
-// A DMT timing.
+/// A DMT timing.
 #[derive(Debug, Copy, Clone, FFIFrom)]
 #[ffi(ffi::dmt::di_dmt_timing)]
 pub struct Timing {
@@ -26,3 +26,176 @@ pub struct Timing {
     pub vert_border: i32,
     pub reduced_blanking: bool,
 }
+
+impl Timing {
+    /// Iterate over all DMT timings, by increasing DMT ID.
+    pub fn all() -> impl Iterator<Item = Timing> {
+        TIMINGS.iter().copied()
+    }
+
+    /// Get a DMT timing from its DMT ID.
+    ///
+    /// Returns `None` if the DMT ID is unknown.
+    pub fn from_id(dmt_id: u8) -> Option<Timing> {
+        Timing::all().find(|timing| timing.dmt_id == dmt_id)
+    }
+
+    /// Get a DMT timing from its 2-byte EDID standard timing code.
+    ///
+    /// Returns `None` if no DMT timing has this code.
+    pub fn from_std_id(edid_std_id: u16) -> Option<Timing> {
+        Timing::all().find(|timing| timing.edid_std_id != 0 && timing.edid_std_id == edid_std_id)
+    }
+
+    /// Get a DMT timing from its 3-byte CVT code.
+    ///
+    /// Returns `None` if no DMT timing has this code.
+    pub fn from_cvt_id(cvt_id: u32) -> Option<Timing> {
+        Timing::all().find(|timing| timing.cvt_id != 0 && timing.cvt_id == cvt_id)
+    }
+
+    /// Find the DMT timing with the given resolution, the closest refresh
+    /// rate within 0.5 Hz and the given blanking.
+    ///
+    /// Returns `None` if no DMT timing matches.
+    pub fn find(
+        horiz_video: i32,
+        vert_video: i32,
+        refresh_rate_hz: f32,
+        reduced_blanking: bool,
+    ) -> Option<Timing> {
+        let distance = |timing: &Timing| (timing.refresh_rate_hz - refresh_rate_hz).abs();
+        Timing::all()
+            .filter(|timing| {
+                timing.horiz_video == horiz_video
+                    && timing.vert_video == vert_video
+                    && timing.reduced_blanking == reduced_blanking
+                    && distance(timing) < 0.5
+            })
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+macro_rules! dmt {
+    (
+        $id:expr, $std_id:expr, $cvt_id:expr, $h:expr, $v:expr, $refresh:expr, $clock:expr,
+        $h_blank:expr, $v_blank:expr, $h_front:expr, $v_front:expr, $h_sync:expr, $v_sync:expr,
+        $h_border:expr, $v_border:expr, $rb:expr
+    ) => {
+        Timing {
+            dmt_id: $id,
+            edid_std_id: $std_id,
+            cvt_id: $cvt_id,
+            horiz_video: $h,
+            vert_video: $v,
+            refresh_rate_hz: $refresh,
+            pixel_clock_hz: $clock,
+            horiz_blank: $h_blank,
+            vert_blank: $v_blank,
+            horiz_front_porch: $h_front,
+            vert_front_porch: $v_front,
+            horiz_sync_pulse: $h_sync,
+            vert_sync_pulse: $v_sync,
+            horiz_border: $h_border,
+            vert_border: $v_border,
+            reduced_blanking: $rb,
+        }
+    };
+}
+
+/// All DMT timings, sorted by DMT ID.
+///
+/// Columns: DMT ID, standard timing ID, CVT ID, active pixels and lines,
+/// refresh rate, pixel clock, blanking, front porches, sync pulses, borders,
+/// reduced blanking.
+#[rustfmt::skip]
+static TIMINGS: [Timing; 88] = [
+    dmt!(0x01, 0x0000, 0x000000, 640, 350, 85.0, 31500000, 192, 95, 32, 32, 64, 3, 0, 0, false),
+    dmt!(0x02, 0x0000, 0x000000, 640, 400, 85.0, 31500000, 192, 45, 32, 1, 64, 3, 0, 0, false),
+    dmt!(0x03, 0x0000, 0x000000, 720, 400, 85.0, 35500000, 216, 46, 36, 1, 72, 3, 0, 0, false),
+    dmt!(0x04, 0x3140, 0x000000, 640, 480, 60.0, 25175000, 144, 29, 8, 2, 96, 2, 8, 8, false),
+    dmt!(0x05, 0x314c, 0x000000, 640, 480, 72.0, 31500000, 176, 24, 16, 1, 40, 3, 8, 8, false),
+    dmt!(0x06, 0x314f, 0x000000, 640, 480, 75.0, 31500000, 200, 20, 16, 1, 64, 3, 0, 0, false),
+    dmt!(0x07, 0x3159, 0x000000, 640, 480, 85.0, 36000000, 192, 29, 56, 1, 56, 3, 0, 0, false),
+    dmt!(0x08, 0x0000, 0x000000, 800, 600, 56.0, 36000000, 224, 25, 24, 1, 72, 2, 0, 0, false),
+    dmt!(0x09, 0x4540, 0x000000, 800, 600, 60.0, 40000000, 256, 28, 40, 1, 128, 4, 0, 0, false),
+    dmt!(0x0a, 0x454c, 0x000000, 800, 600, 72.0, 50000000, 240, 66, 56, 37, 120, 6, 0, 0, false),
+    dmt!(0x0b, 0x454f, 0x000000, 800, 600, 75.0, 49500000, 256, 25, 16, 1, 80, 3, 0, 0, false),
+    dmt!(0x0c, 0x4559, 0x000000, 800, 600, 85.0, 56250000, 248, 31, 32, 1, 64, 3, 0, 0, false),
+    dmt!(0x0d, 0x0000, 0x000000, 800, 600, 120.0, 73250000, 160, 36, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x0e, 0x0000, 0x000000, 848, 480, 60.0, 33750000, 240, 37, 16, 6, 112, 8, 0, 0, false),
+    dmt!(0x0f, 0x0000, 0x000000, 1024, 768, 43.0, 44900000, 240, 24, 8, 0, 176, 4, 0, 0, false),
+    dmt!(0x10, 0x6140, 0x000000, 1024, 768, 60.0, 65000000, 320, 38, 24, 3, 136, 6, 0, 0, false),
+    dmt!(0x11, 0x614a, 0x000000, 1024, 768, 70.0, 75000000, 304, 38, 24, 3, 136, 6, 0, 0, false),
+    dmt!(0x12, 0x614f, 0x000000, 1024, 768, 75.0, 78750000, 288, 32, 16, 1, 96, 3, 0, 0, false),
+    dmt!(0x13, 0x6159, 0x000000, 1024, 768, 85.0, 94500000, 352, 40, 48, 1, 96, 3, 0, 0, false),
+    dmt!(0x14, 0x0000, 0x000000, 1024, 768, 120.0, 115500000, 160, 45, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x15, 0x714f, 0x000000, 1152, 864, 75.0, 108000000, 448, 36, 64, 1, 128, 3, 0, 0, false),
+    dmt!(0x16, 0x0000, 0x7f1c21, 1280, 768, 60.0, 68250000, 160, 22, 48, 3, 32, 7, 0, 0, true),
+    dmt!(0x17, 0x0000, 0x7f1c28, 1280, 768, 60.0, 79500000, 384, 30, 64, 3, 128, 7, 0, 0, false),
+    dmt!(0x18, 0x0000, 0x7f1c44, 1280, 768, 75.0, 102250000, 416, 37, 80, 3, 128, 7, 0, 0, false),
+    dmt!(0x19, 0x0000, 0x7f1c62, 1280, 768, 85.0, 117500000, 432, 41, 80, 3, 136, 7, 0, 0, false),
+    dmt!(0x1a, 0x0000, 0x000000, 1280, 768, 120.0, 140250000, 160, 45, 48, 3, 32, 7, 0, 0, true),
+    dmt!(0x1b, 0x0000, 0x8f1821, 1280, 800, 60.0, 71000000, 160, 23, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x1c, 0x8100, 0x8f1828, 1280, 800, 60.0, 83500000, 400, 31, 72, 3, 128, 6, 0, 0, false),
+    dmt!(0x1d, 0x810f, 0x8f1844, 1280, 800, 75.0, 106500000, 416, 38, 80, 3, 128, 6, 0, 0, false),
+    dmt!(0x1e, 0x8119, 0x8f1862, 1280, 800, 85.0, 122500000, 432, 43, 80, 3, 136, 6, 0, 0, false),
+    dmt!(0x1f, 0x0000, 0x000000, 1280, 800, 120.0, 146250000, 160, 47, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x20, 0x8140, 0x000000, 1280, 960, 60.0, 108000000, 520, 40, 96, 1, 112, 3, 0, 0, false),
+    dmt!(0x21, 0x8159, 0x000000, 1280, 960, 85.0, 148500000, 448, 51, 64, 1, 160, 3, 0, 0, false),
+    dmt!(0x22, 0x0000, 0x000000, 1280, 960, 120.0, 175500000, 160, 57, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x23, 0x8180, 0x000000, 1280, 1024, 60.0, 108000000, 408, 42, 48, 1, 112, 3, 0, 0, false),
+    dmt!(0x24, 0x818f, 0x000000, 1280, 1024, 75.0, 135000000, 408, 42, 16, 1, 144, 3, 0, 0, false),
+    dmt!(0x25, 0x8199, 0x000000, 1280, 1024, 85.0, 157500000, 448, 48, 64, 1, 160, 3, 0, 0, false),
+    dmt!(0x26, 0x0000, 0x000000, 1280, 1024, 120.0, 187250000, 160, 60, 48, 3, 32, 7, 0, 0, true),
+    dmt!(0x27, 0x0000, 0x000000, 1360, 768, 60.0, 85500000, 432, 27, 64, 3, 112, 6, 0, 0, false),
+    dmt!(0x28, 0x0000, 0x000000, 1360, 768, 120.0, 148250000, 160, 45, 48, 3, 32, 5, 0, 0, true),
+    dmt!(0x29, 0x0000, 0x0c2021, 1400, 1050, 60.0, 101000000, 160, 30, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x2a, 0x9040, 0x0c2028, 1400, 1050, 60.0, 121750000, 464, 39, 88, 3, 144, 4, 0, 0, false),
+    dmt!(0x2b, 0x904f, 0x0c2044, 1400, 1050, 75.0, 156000000, 496, 49, 104, 3, 144, 4, 0, 0, false),
+    dmt!(0x2c, 0x9059, 0x0c2062, 1400, 1050, 85.0, 179500000, 512, 55, 104, 3, 152, 4, 0, 0, false),
+    dmt!(0x2d, 0x0000, 0x000000, 1400, 1050, 120.0, 208000000, 160, 62, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x2e, 0x0000, 0xc11821, 1440, 900, 60.0, 88750000, 160, 26, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x2f, 0x9500, 0xc11828, 1440, 900, 60.0, 106500000, 464, 34, 80, 3, 152, 6, 0, 0, false),
+    dmt!(0x30, 0x950f, 0xc11844, 1440, 900, 75.0, 136750000, 496, 42, 96, 3, 152, 6, 0, 0, false),
+    dmt!(0x31, 0x9519, 0xc11862, 1440, 900, 85.0, 157000000, 512, 48, 104, 3, 152, 6, 0, 0, false),
+    dmt!(0x32, 0x0000, 0x000000, 1440, 900, 120.0, 182750000, 160, 53, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x33, 0xa940, 0x000000, 1600, 1200, 60.0, 162000000, 560, 50, 64, 1, 192, 3, 0, 0, false),
+    dmt!(0x34, 0xa945, 0x000000, 1600, 1200, 65.0, 175500000, 560, 50, 64, 1, 192, 3, 0, 0, false),
+    dmt!(0x35, 0xa94a, 0x000000, 1600, 1200, 70.0, 189000000, 560, 50, 64, 1, 192, 3, 0, 0, false),
+    dmt!(0x36, 0xa94f, 0x000000, 1600, 1200, 75.0, 202500000, 560, 50, 64, 1, 192, 3, 0, 0, false),
+    dmt!(0x37, 0xa959, 0x000000, 1600, 1200, 85.0, 229500000, 560, 50, 64, 1, 192, 3, 0, 0, false),
+    dmt!(0x38, 0x0000, 0x000000, 1600, 1200, 120.0, 268250000, 160, 71, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x39, 0x0000, 0x0c2821, 1680, 1050, 60.0, 119000000, 160, 30, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x3a, 0xb300, 0x0c2828, 1680, 1050, 60.0, 146250000, 560, 39, 104, 3, 176, 6, 0, 0, false),
+    dmt!(0x3b, 0xb30f, 0x0c2844, 1680, 1050, 75.0, 187000000, 592, 49, 120, 3, 176, 6, 0, 0, false),
+    dmt!(0x3c, 0xb319, 0x0c2862, 1680, 1050, 85.0, 214750000, 608, 55, 128, 3, 176, 6, 0, 0, false),
+    dmt!(0x3d, 0x0000, 0x000000, 1680, 1050, 120.0, 245500000, 160, 62, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x3e, 0xc140, 0x000000, 1792, 1344, 60.0, 204750000, 656, 50, 128, 1, 200, 3, 0, 0, false),
+    dmt!(0x3f, 0xc14f, 0x000000, 1792, 1344, 75.0, 261000000, 664, 73, 96, 1, 216, 3, 0, 0, false),
+    dmt!(0x40, 0x0000, 0x000000, 1792, 1344, 120.0, 333250000, 160, 79, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x41, 0xc940, 0x000000, 1856, 1392, 60.0, 218250000, 672, 47, 96, 1, 224, 3, 0, 0, false),
+    dmt!(0x42, 0xc94f, 0x000000, 1856, 1392, 75.0, 288000000, 704, 108, 128, 1, 224, 3, 0, 0, false),
+    dmt!(0x43, 0x0000, 0x000000, 1856, 1392, 120.0, 356500000, 160, 82, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x44, 0x0000, 0x572821, 1920, 1200, 60.0, 154000000, 160, 35, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x45, 0xd100, 0x572828, 1920, 1200, 60.0, 193250000, 672, 45, 136, 3, 200, 6, 0, 0, false),
+    dmt!(0x46, 0xd10f, 0x572844, 1920, 1200, 75.0, 245250000, 688, 55, 136, 3, 208, 6, 0, 0, false),
+    dmt!(0x47, 0xd119, 0x572862, 1920, 1200, 85.0, 281250000, 704, 62, 144, 3, 208, 6, 0, 0, false),
+    dmt!(0x48, 0x0000, 0x000000, 1920, 1200, 120.0, 317000000, 160, 71, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x49, 0xd140, 0x000000, 1920, 1440, 60.0, 234000000, 680, 60, 128, 1, 208, 3, 0, 0, false),
+    dmt!(0x4a, 0xd14f, 0x000000, 1920, 1440, 75.0, 297000000, 720, 60, 144, 1, 224, 3, 0, 0, false),
+    dmt!(0x4b, 0x0000, 0x000000, 1920, 1440, 120.0, 380500000, 160, 85, 48, 3, 32, 4, 0, 0, true),
+    dmt!(0x4c, 0x0000, 0x1f3821, 2560, 1600, 60.0, 268500000, 160, 46, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x4d, 0x0000, 0x1f3828, 2560, 1600, 60.0, 348500000, 944, 58, 192, 3, 280, 6, 0, 0, false),
+    dmt!(0x4e, 0x0000, 0x1f3844, 2560, 1600, 75.0, 443250000, 976, 72, 208, 3, 280, 6, 0, 0, false),
+    dmt!(0x4f, 0x0000, 0x1f3862, 2560, 1600, 85.0, 505250000, 976, 82, 208, 3, 280, 6, 0, 0, false),
+    dmt!(0x50, 0x0000, 0x000000, 2560, 1600, 120.0, 552750000, 160, 94, 48, 3, 32, 6, 0, 0, true),
+    dmt!(0x51, 0x0000, 0x000000, 1366, 768, 60.0, 85500000, 426, 30, 70, 3, 143, 3, 0, 0, false),
+    dmt!(0x52, 0xd1c0, 0x000000, 1920, 1080, 60.0, 148500000, 280, 45, 88, 4, 44, 5, 0, 0, false),
+    dmt!(0x53, 0xa9c0, 0x000000, 1600, 900, 60.0, 108000000, 200, 100, 24, 1, 80, 3, 0, 0, true),
+    dmt!(0x54, 0xe1c0, 0x000000, 2048, 1152, 60.0, 162000000, 202, 48, 26, 1, 80, 3, 0, 0, true),
+    dmt!(0x55, 0x81c0, 0x000000, 1280, 720, 60.0, 74250000, 370, 30, 110, 5, 40, 5, 0, 0, false),
+    dmt!(0x56, 0x0000, 0x000000, 1366, 768, 60.0, 72000000, 134, 32, 14, 1, 56, 3, 0, 0, true),
+    dmt!(0x57, 0x0000, 0x000000, 4096, 2160, 60.0, 556744000, 80, 62, 8, 48, 32, 8, 0, 0, true),
+    dmt!(0x58, 0x0000, 0x000000, 4096, 2160, 59.94, 556188000, 80, 62, 8, 48, 32, 8, 0, 0, true),
+];
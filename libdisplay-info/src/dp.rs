@@ -0,0 +1,331 @@
+//! DisplayPort link bandwidth computations.
+//!
+//! The link capabilities come from the DPCD of the sink and from the source,
+//! and are supplied by the caller. The color depth accepted by the sink comes
+//! from its EDID, see [`SinkCaps::new`].
+use crate::{
+    edid::VideoInputDigital,
+    mode::{Mode, PixelEncoding},
+};
+
+/// Share of the link bandwidth lost to the down-spreading of the link clock.
+const DOWNSPREAD: f64 = 0.005;
+
+/// Share of the bandwidth of an 8b/10b link left once the Forward Error
+/// Correction parity and markers are inserted.
+const FEC_8B10B_EFFICIENCY: f64 = 0.972_261;
+
+/// Number of time slots of a Multi-Stream Transport Packet.
+const MTP_TIME_SLOTS: f64 = 64.0;
+
+/// Bit depth assumed when the EDID doesn't provide any.
+const DEFAULT_MAX_BPC: u8 = 8;
+
+/// Channel coding of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelCoding {
+    /// 8b/10b, used by DP 1.x link rates
+    _8b10b,
+    /// 128b/132b, used by DP 2.x UHBR link rates
+    _128b132b,
+}
+
+/// Link rate per lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LinkRate {
+    /// Reduced Bit Rate, 1.62 Gbps
+    Rbr,
+    /// High Bit Rate, 2.7 Gbps
+    Hbr,
+    /// High Bit Rate 2, 5.4 Gbps
+    Hbr2,
+    /// High Bit Rate 3, 8.1 Gbps
+    Hbr3,
+    /// Ultra High Bit Rate, 10 Gbps
+    Uhbr10,
+    /// Ultra High Bit Rate, 13.5 Gbps
+    Uhbr13_5,
+    /// Ultra High Bit Rate, 20 Gbps
+    Uhbr20,
+}
+
+impl LinkRate {
+    /// Get the bit rate per lane in bits per second.
+    pub fn bps(&self) -> i64 {
+        match self {
+            LinkRate::Rbr => 1_620_000_000,
+            LinkRate::Hbr => 2_700_000_000,
+            LinkRate::Hbr2 => 5_400_000_000,
+            LinkRate::Hbr3 => 8_100_000_000,
+            LinkRate::Uhbr10 => 10_000_000_000,
+            LinkRate::Uhbr13_5 => 13_500_000_000,
+            LinkRate::Uhbr20 => 20_000_000_000,
+        }
+    }
+
+    /// Get the channel coding used at this link rate.
+    pub fn channel_coding(&self) -> ChannelCoding {
+        match self {
+            LinkRate::Rbr | LinkRate::Hbr | LinkRate::Hbr2 | LinkRate::Hbr3 => {
+                ChannelCoding::_8b10b
+            }
+            LinkRate::Uhbr10 | LinkRate::Uhbr13_5 | LinkRate::Uhbr20 => ChannelCoding::_128b132b,
+        }
+    }
+}
+
+/// Capabilities of a DisplayPort link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkCaps {
+    /// Number of lanes, 1, 2 or 4
+    pub lane_count: u8,
+    pub link_rate: LinkRate,
+    /// Whether both the source and the sink support DSC
+    pub supports_dsc: bool,
+    /// Whether the link uses Multi-Stream Transport (MST), e.g. to a dock or
+    /// a daisy chain
+    pub mst: bool,
+}
+
+impl LinkCaps {
+    /// Get the bandwidth available to video streams, in bits per second.
+    ///
+    /// The channel coding overhead and the down-spreading of the link clock
+    /// are accounted for. MST 8b/10b links reserve the first time slot of
+    /// each MTP for its header. The bandwidth is shared by all the streams of
+    /// an MST link.
+    pub fn payload_bps(&self) -> f64 {
+        let efficiency = match self.link_rate.channel_coding() {
+            ChannelCoding::_8b10b if self.mst => {
+                8.0 / 10.0 * (MTP_TIME_SLOTS - 1.0) / MTP_TIME_SLOTS
+            }
+            ChannelCoding::_8b10b => 8.0 / 10.0,
+            ChannelCoding::_128b132b => 128.0 / 132.0,
+        };
+        self.lane_count as f64 * self.link_rate.bps() as f64 * efficiency * (1.0 - DOWNSPREAD)
+    }
+
+    /// Get the bandwidth available to compressed video streams, in bits per
+    /// second.
+    ///
+    /// DSC requires FEC on 8b/10b links.
+    fn dsc_payload_bps(&self) -> f64 {
+        match self.link_rate.channel_coding() {
+            ChannelCoding::_8b10b => self.payload_bps() * FEC_8B10B_EFFICIENCY,
+            ChannelCoding::_128b132b => self.payload_bps(),
+        }
+    }
+}
+
+/// Capabilities of a DisplayPort sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkCaps {
+    /// Maximum number of bits per component
+    pub max_bpc: u8,
+}
+
+impl SinkCaps {
+    /// Collect the capabilities of a sink from its EDID.
+    ///
+    /// The maximum bit depth is the lowest of the color bit depth of the
+    /// digital video input and of the interface bit depth of the VESA Display
+    /// Device Data Block. The native bit depth of the DisplayID display
+    /// parameters is only used when neither is available, as sinks usually
+    /// accept deeper signals than their panel. Without any of them, 8 bits
+    /// are assumed.
+    pub fn new(
+        video_input: Option<&VideoInputDigital>,
+        dddb_bit_depth_interface: Option<i32>,
+        displayid_bits_per_color_native: Option<i32>,
+    ) -> Self {
+        let valid = |bpc: i32| u8::try_from(bpc).ok().filter(|bpc| *bpc > 0);
+        let interface = [
+            video_input.and_then(|input| input.color_bit_depth),
+            dddb_bit_depth_interface,
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(valid)
+        .min();
+
+        SinkCaps {
+            max_bpc: interface
+                .or_else(|| displayid_bits_per_color_native.and_then(valid))
+                .unwrap_or(DEFAULT_MAX_BPC),
+        }
+    }
+}
+
+/// Result of a DisplayPort bandwidth check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feasibility {
+    /// The mode fits uncompressed
+    Fits {
+        /// Bandwidth required by the mode, in bits per second
+        required_bps: f64,
+        /// Bandwidth available on the link, in bits per second
+        available_bps: f64,
+    },
+    /// The mode only fits with DSC
+    NeedsDsc,
+    /// The sink doesn't accept the bit depth
+    UnsupportedBpc,
+    /// The mode doesn't fit, even with DSC
+    InsufficientBandwidth,
+}
+
+/// Get the bandwidth required by a mode in bits per second.
+pub fn required_bps(mode: &Mode, encoding: PixelEncoding, bpc: u8) -> f64 {
+    mode.pixel_clock_hz as f64 * encoding.bits_per_pixel(bpc)
+}
+
+/// Check whether a mode fits on a DisplayPort link.
+///
+/// With DSC, the check uses the lowest compressed bit rate, 8 bits per pixel
+/// or 6 bits per pixel for YCbCr 4:2:0. On MST links, the mode is checked
+/// against the whole link, other streams must be accounted for by the caller.
+pub fn check(
+    mode: &Mode,
+    encoding: PixelEncoding,
+    bpc: u8,
+    link: &LinkCaps,
+    sink: &SinkCaps,
+) -> Feasibility {
+    if bpc > sink.max_bpc {
+        return Feasibility::UnsupportedBpc;
+    }
+
+    let required_bps = required_bps(mode, encoding, bpc);
+    let available_bps = link.payload_bps();
+    if required_bps <= available_bps {
+        return Feasibility::Fits {
+            required_bps,
+            available_bps,
+        };
+    }
+
+    let min_dsc_bits_per_pixel = match encoding {
+        PixelEncoding::Ycbcr420 => 6.0,
+        _ => 8.0,
+    };
+    if link.supports_dsc
+        && mode.pixel_clock_hz as f64 * min_dsc_bits_per_pixel <= link.dsc_payload_bps()
+    {
+        return Feasibility::NeedsDsc;
+    }
+    Feasibility::InsufficientBandwidth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edid::VideoInputDigitalInterface, mode::SyncPolarity};
+
+    /// A 3840x2160 mode with the CTA-861 blanking of VIC 97.
+    fn mode_4k(pixel_clock_hz: i64) -> Mode {
+        Mode {
+            pixel_clock_hz,
+            h_active: 3840,
+            h_front: 176,
+            h_sync: 88,
+            h_back: 296,
+            v_active: 2160,
+            v_front: 8,
+            v_sync: 10,
+            v_back: 72,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    fn link(lane_count: u8, link_rate: LinkRate, mst: bool) -> LinkCaps {
+        LinkCaps {
+            lane_count,
+            link_rate,
+            supports_dsc: true,
+            mst,
+        }
+    }
+
+    #[test]
+    fn payload() {
+        let mbps = |link: LinkCaps| link.payload_bps() as i64 / 1_000_000;
+        assert_eq!(mbps(link(4, LinkRate::Hbr2, false)), 17_193);
+        assert_eq!(mbps(link(4, LinkRate::Hbr2, true)), 16_924);
+        assert_eq!(mbps(link(1, LinkRate::Rbr, false)), 1_289);
+        assert_eq!(mbps(link(4, LinkRate::Uhbr10, false)), 38_593);
+        assert_eq!(mbps(link(4, LinkRate::Uhbr10, true)), 38_593);
+    }
+
+    #[test]
+    fn sink_caps() {
+        let video_input = |color_bit_depth| VideoInputDigital {
+            dfp1: false,
+            color_bit_depth,
+            interface: VideoInputDigitalInterface::DisplayPort,
+        };
+        let max_bpc = |input: Option<&VideoInputDigital>, dddb, native| {
+            SinkCaps::new(input, dddb, native).max_bpc
+        };
+
+        assert_eq!(max_bpc(None, None, None), 8);
+        assert_eq!(max_bpc(None, None, Some(6)), 6);
+        assert_eq!(max_bpc(Some(&video_input(Some(10))), None, Some(8)), 10);
+        assert_eq!(max_bpc(None, Some(12), Some(10)), 12);
+        assert_eq!(max_bpc(Some(&video_input(Some(10))), Some(12), None), 10);
+        assert_eq!(max_bpc(Some(&video_input(None)), Some(0), Some(10)), 10);
+    }
+
+    #[test]
+    fn feasibility() {
+        let sink = SinkCaps { max_bpc: 10 };
+        let hbr2 = link(4, LinkRate::Hbr2, false);
+        assert!(matches!(
+            check(&mode_4k(594_000_000), PixelEncoding::Rgb, 8, &hbr2, &sink),
+            Feasibility::Fits { .. }
+        ));
+        assert_eq!(
+            check(&mode_4k(594_000_000), PixelEncoding::Rgb, 10, &hbr2, &sink),
+            Feasibility::NeedsDsc
+        );
+        assert_eq!(
+            check(&mode_4k(594_000_000), PixelEncoding::Rgb, 12, &hbr2, &sink),
+            Feasibility::UnsupportedBpc
+        );
+
+        // The MTP header leaves no room for a 17.09 Gbps mode.
+        let no_dsc = |link: LinkCaps| LinkCaps {
+            supports_dsc: false,
+            ..link
+        };
+        assert!(matches!(
+            check(
+                &mode_4k(712_000_000),
+                PixelEncoding::Rgb,
+                8,
+                &no_dsc(hbr2),
+                &sink
+            ),
+            Feasibility::Fits { .. }
+        ));
+        assert_eq!(
+            check(
+                &mode_4k(712_000_000),
+                PixelEncoding::Rgb,
+                8,
+                &no_dsc(link(4, LinkRate::Hbr2, true)),
+                &sink
+            ),
+            Feasibility::InsufficientBandwidth
+        );
+
+        // FEC leaves no room for 2.12 Gbps of compressed video.
+        let hbr = link(1, LinkRate::Hbr, false);
+        assert!(hbr.payload_bps() >= 265_000_000.0 * 8.0);
+        assert_eq!(
+            check(&mode_4k(265_000_000), PixelEncoding::Rgb, 10, &hbr, &sink),
+            Feasibility::InsufficientBandwidth
+        );
+    }
+}
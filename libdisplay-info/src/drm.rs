@@ -0,0 +1,391 @@
+//! Conversion of timings to DRM modes.
+//!
+//! [`ModeInfo`] has the layout of the kernel's `struct drm_mode_modeinfo`, and
+//! can be passed as-is to the mode setting ioctls or used as the content of a
+//! `MODE_ID` property blob.
+use std::ffi::c_char;
+
+use crate::{
+    cta::{VideoFormat, VideoFormatPictureAspectRatio},
+    cvt,
+    displayid::{TimingAspectRatio, TypeIIIVIITiming},
+    dmt,
+    edid::DetailedTimingDef,
+    gtf,
+    mode::{Mode, SyncPolarity},
+};
+
+/// Length of the mode name, including the NUL terminator.
+pub const DISPLAY_MODE_LEN: usize = 32;
+
+bitflags::bitflags! {
+    /// `DRM_MODE_FLAG_*` bits.
+    ///
+    /// The stereo 3D layout and picture aspect ratio are multi-bit fields of
+    /// the same integer, see [`ModeInfo::stereo_3d`] and
+    /// [`ModeInfo::picture_aspect_ratio`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[repr(transparent)]
+    pub struct ModeFlags: u32 {
+        const PHSYNC = 1 << 0;
+        const NHSYNC = 1 << 1;
+        const PVSYNC = 1 << 2;
+        const NVSYNC = 1 << 3;
+        const INTERLACE = 1 << 4;
+        const DBLSCAN = 1 << 5;
+        const CSYNC = 1 << 6;
+        const PCSYNC = 1 << 7;
+        const NCSYNC = 1 << 8;
+        const HSKEW = 1 << 9;
+        const BCAST = 1 << 10;
+        const PIXMUX = 1 << 11;
+        const DBLCLK = 1 << 12;
+        const CLKDIV2 = 1 << 13;
+        const _ = !0;
+    }
+}
+
+bitflags::bitflags! {
+    /// `DRM_MODE_TYPE_*` bits.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[repr(transparent)]
+    pub struct ModeType: u32 {
+        const BUILTIN = 1 << 0;
+        const CLOCK_C = (1 << 1) | Self::BUILTIN.bits();
+        const CRTC_C = (1 << 2) | Self::BUILTIN.bits();
+        const PREFERRED = 1 << 3;
+        const DEFAULT = 1 << 4;
+        const USERDEF = 1 << 5;
+        const DRIVER = 1 << 6;
+        const _ = !0;
+    }
+}
+
+const FLAG_3D_SHIFT: u32 = 14;
+const FLAG_3D_MASK: u32 = 0x1f << FLAG_3D_SHIFT;
+const FLAG_PIC_AR_SHIFT: u32 = 19;
+const FLAG_PIC_AR_MASK: u32 = 0x0f << FLAG_PIC_AR_SHIFT;
+
+/// Stereo 3D layout, `DRM_MODE_FLAG_3D_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stereo3d {
+    None,
+    FramePacking,
+    FieldAlternative,
+    LineAlternative,
+    SideBySideFull,
+    LDepth,
+    LDepthGfxGfxDepth,
+    TopAndBottom,
+    SideBySideHalf,
+}
+
+impl Stereo3d {
+    fn bits(&self) -> u32 {
+        let value = match self {
+            Stereo3d::None => 0,
+            Stereo3d::FramePacking => 1,
+            Stereo3d::FieldAlternative => 2,
+            Stereo3d::LineAlternative => 3,
+            Stereo3d::SideBySideFull => 4,
+            Stereo3d::LDepth => 5,
+            Stereo3d::LDepthGfxGfxDepth => 6,
+            Stereo3d::TopAndBottom => 7,
+            Stereo3d::SideBySideHalf => 8,
+        };
+        value << FLAG_3D_SHIFT
+    }
+}
+
+/// Picture aspect ratio, `DRM_MODE_FLAG_PIC_AR_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PictureAspectRatio {
+    None,
+    _4_3,
+    _16_9,
+    _64_27,
+    _256_135,
+}
+
+impl PictureAspectRatio {
+    fn bits(&self) -> u32 {
+        let value = match self {
+            PictureAspectRatio::None => 0,
+            PictureAspectRatio::_4_3 => 1,
+            PictureAspectRatio::_16_9 => 2,
+            PictureAspectRatio::_64_27 => 3,
+            PictureAspectRatio::_256_135 => 4,
+        };
+        value << FLAG_PIC_AR_SHIFT
+    }
+}
+
+impl From<VideoFormatPictureAspectRatio> for PictureAspectRatio {
+    fn from(aspect_ratio: VideoFormatPictureAspectRatio) -> Self {
+        match aspect_ratio {
+            VideoFormatPictureAspectRatio::_4_3 => PictureAspectRatio::_4_3,
+            VideoFormatPictureAspectRatio::_16_9 => PictureAspectRatio::_16_9,
+            VideoFormatPictureAspectRatio::_64_27 => PictureAspectRatio::_64_27,
+            VideoFormatPictureAspectRatio::_256_135 => PictureAspectRatio::_256_135,
+        }
+    }
+}
+
+/// A DRM mode, layout-compatible with `struct drm_mode_modeinfo`.
+///
+/// Modes converted from timings have the [`ModeType::DRIVER`] type, and are
+/// named `<width>x<height>`, with an `i` suffix for interlaced modes. Only
+/// DisplayID timings carry the information needed to set
+/// [`ModeType::PREFERRED`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModeInfo {
+    /// Pixel clock in kHz
+    pub clock: u32,
+    pub hdisplay: u16,
+    pub hsync_start: u16,
+    pub hsync_end: u16,
+    pub htotal: u16,
+    pub hskew: u16,
+    pub vdisplay: u16,
+    pub vsync_start: u16,
+    pub vsync_end: u16,
+    pub vtotal: u16,
+    pub vscan: u16,
+    /// Refresh rate in Hz
+    pub vrefresh: u32,
+    pub flags: ModeFlags,
+    pub type_: ModeType,
+    pub name: [c_char; DISPLAY_MODE_LEN],
+}
+
+impl ModeInfo {
+    /// Get the mode name.
+    ///
+    /// Returns `None` if the name isn't NUL-terminated or isn't valid UTF-8.
+    pub fn name(&self) -> Option<&str> {
+        let len = self.name.iter().position(|c| *c == 0)?;
+        let bytes = unsafe { std::slice::from_raw_parts(self.name.as_ptr() as *const u8, len) };
+        std::str::from_utf8(bytes).ok()
+    }
+
+    /// Set the mode name, truncated to 31 bytes.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = [0; DISPLAY_MODE_LEN];
+        for (dst, src) in self
+            .name
+            .iter_mut()
+            .zip(name.bytes().take(DISPLAY_MODE_LEN - 1))
+        {
+            *dst = src as c_char;
+        }
+    }
+
+    /// Get the stereo 3D layout.
+    ///
+    /// Returns `None` for values unknown to this crate.
+    pub fn stereo_3d(&self) -> Option<Stereo3d> {
+        Some(match (self.flags.bits() & FLAG_3D_MASK) >> FLAG_3D_SHIFT {
+            0 => Stereo3d::None,
+            1 => Stereo3d::FramePacking,
+            2 => Stereo3d::FieldAlternative,
+            3 => Stereo3d::LineAlternative,
+            4 => Stereo3d::SideBySideFull,
+            5 => Stereo3d::LDepth,
+            6 => Stereo3d::LDepthGfxGfxDepth,
+            7 => Stereo3d::TopAndBottom,
+            8 => Stereo3d::SideBySideHalf,
+            _ => return None,
+        })
+    }
+
+    /// Set the stereo 3D layout.
+    pub fn set_stereo_3d(&mut self, stereo_3d: Stereo3d) {
+        self.flags =
+            ModeFlags::from_bits_retain(self.flags.bits() & !FLAG_3D_MASK | stereo_3d.bits());
+    }
+
+    /// Get the picture aspect ratio.
+    ///
+    /// Returns `None` for values unknown to this crate.
+    pub fn picture_aspect_ratio(&self) -> Option<PictureAspectRatio> {
+        Some(
+            match (self.flags.bits() & FLAG_PIC_AR_MASK) >> FLAG_PIC_AR_SHIFT {
+                0 => PictureAspectRatio::None,
+                1 => PictureAspectRatio::_4_3,
+                2 => PictureAspectRatio::_16_9,
+                3 => PictureAspectRatio::_64_27,
+                4 => PictureAspectRatio::_256_135,
+                _ => return None,
+            },
+        )
+    }
+
+    /// Set the picture aspect ratio.
+    pub fn set_picture_aspect_ratio(&mut self, aspect_ratio: PictureAspectRatio) {
+        self.flags = ModeFlags::from_bits_retain(
+            self.flags.bits() & !FLAG_PIC_AR_MASK | aspect_ratio.bits(),
+        );
+    }
+}
+
+/// Convert a timing value, saturating at the limits of the DRM fields.
+fn saturate(value: i32) -> u16 {
+    value.clamp(0, u16::MAX as i32) as u16
+}
+
+impl From<Mode> for ModeInfo {
+    fn from(mode: Mode) -> Self {
+        let mut flags = match mode.h_sync_polarity {
+            SyncPolarity::Positive => ModeFlags::PHSYNC,
+            SyncPolarity::Negative => ModeFlags::NHSYNC,
+        };
+        flags |= match mode.v_sync_polarity {
+            SyncPolarity::Positive => ModeFlags::PVSYNC,
+            SyncPolarity::Negative => ModeFlags::NVSYNC,
+        };
+        if mode.interlaced {
+            flags |= ModeFlags::INTERLACE;
+        }
+
+        let (numerator, denominator) = mode.refresh_rate();
+        let mut info = ModeInfo {
+            clock: ((mode.pixel_clock_hz.max(0) + 500) / 1000).min(u32::MAX as i64) as u32,
+            hdisplay: saturate(mode.h_active),
+            hsync_start: saturate(mode.h_active + mode.h_front),
+            hsync_end: saturate(mode.h_active + mode.h_front + mode.h_sync),
+            htotal: saturate(mode.h_total()),
+            hskew: 0,
+            vdisplay: saturate(mode.v_active),
+            vsync_start: saturate(mode.v_active + mode.v_front),
+            vsync_end: saturate(mode.v_active + mode.v_front + mode.v_sync),
+            vtotal: saturate(mode.v_total()),
+            vscan: 0,
+            vrefresh: ((numerator + denominator / 2) / denominator).min(u32::MAX as u64) as u32,
+            flags,
+            type_: ModeType::DRIVER,
+            name: [0; DISPLAY_MODE_LEN],
+        };
+        info.set_name(&format!(
+            "{}x{}{}",
+            mode.h_active,
+            mode.v_active,
+            if mode.interlaced { "i" } else { "" }
+        ));
+        info
+    }
+}
+
+impl From<VideoFormat> for ModeInfo {
+    /// The picture aspect ratio of the video format is included in the flags.
+    fn from(format: VideoFormat) -> Self {
+        let mut info = ModeInfo::from(Mode::from(format));
+        info.set_picture_aspect_ratio(format.picture_aspect_ratio.into());
+        info
+    }
+}
+
+impl From<DetailedTimingDef> for ModeInfo {
+    /// Whether a detailed timing definition is the preferred timing depends
+    /// on its position in the EDID, callers need to set
+    /// [`ModeType::PREFERRED`] on the mode of the first one.
+    fn from(timing: DetailedTimingDef) -> Self {
+        Mode::from(timing).into()
+    }
+}
+
+impl From<dmt::Timing> for ModeInfo {
+    fn from(timing: dmt::Timing) -> Self {
+        Mode::from(timing).into()
+    }
+}
+
+impl From<cvt::Timing> for ModeInfo {
+    fn from(timing: cvt::Timing) -> Self {
+        Mode::from(timing).into()
+    }
+}
+
+impl From<gtf::Timing> for ModeInfo {
+    fn from(timing: gtf::Timing) -> Self {
+        Mode::from(timing).into()
+    }
+}
+
+impl From<TypeIIIVIITiming> for ModeInfo {
+    /// The mode has the [`ModeType::PREFERRED`] type if the timing is
+    /// preferred, and the picture aspect ratio of the timing is included in
+    /// the flags if DRM can represent it.
+    fn from(timing: TypeIIIVIITiming) -> Self {
+        let mut info = ModeInfo::from(Mode::from(timing));
+        if timing.preferred {
+            info.type_ |= ModeType::PREFERRED;
+        }
+        info.set_picture_aspect_ratio(match timing.aspect_ratio {
+            TimingAspectRatio::_4_3 => PictureAspectRatio::_4_3,
+            TimingAspectRatio::_16_9 => PictureAspectRatio::_16_9,
+            TimingAspectRatio::_64_27 => PictureAspectRatio::_64_27,
+            TimingAspectRatio::_256_135 => PictureAspectRatio::_256_135,
+            TimingAspectRatio::_1_1
+            | TimingAspectRatio::_5_4
+            | TimingAspectRatio::_15_9
+            | TimingAspectRatio::_16_10
+            | TimingAspectRatio::Undefined => PictureAspectRatio::None,
+        });
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{displayid::tests::timing_1080p60, edid::tests::dtd_1080p60};
+
+    #[test]
+    fn detailed_timing_def() {
+        let info = ModeInfo::from(dtd_1080p60());
+        assert_eq!(info.name(), Some("1920x1080"));
+        assert_eq!(info.clock, 148_500);
+        assert_eq!(
+            (info.hsync_start, info.hsync_end, info.htotal),
+            (2008, 2052, 2200)
+        );
+        assert_eq!(
+            (info.vsync_start, info.vsync_end, info.vtotal),
+            (1084, 1089, 1125)
+        );
+        assert_eq!(info.vrefresh, 60);
+        assert_eq!(info.flags, ModeFlags::PHSYNC | ModeFlags::PVSYNC);
+        assert_eq!(info.type_, ModeType::DRIVER);
+    }
+
+    #[test]
+    fn displayid_timing() {
+        let info = ModeInfo::from(timing_1080p60());
+        assert_eq!(info.type_, ModeType::DRIVER | ModeType::PREFERRED);
+        assert_eq!(info.picture_aspect_ratio(), Some(PictureAspectRatio::_16_9));
+        assert_eq!(
+            info.flags & !ModeFlags::from_bits_retain(FLAG_PIC_AR_MASK),
+            ModeFlags::PHSYNC | ModeFlags::PVSYNC
+        );
+
+        let mut timing = timing_1080p60();
+        timing.preferred = false;
+        timing.aspect_ratio = TimingAspectRatio::_16_10;
+        let info = ModeInfo::from(timing);
+        assert_eq!(info.type_, ModeType::DRIVER);
+        assert_eq!(info.picture_aspect_ratio(), Some(PictureAspectRatio::None));
+    }
+
+    #[test]
+    fn layout() {
+        let info = ModeInfo::from(dtd_1080p60());
+        let base = std::ptr::addr_of!(info) as usize;
+        let offset = |field: *const u8| field as usize - base;
+        assert_eq!(std::mem::size_of::<ModeInfo>(), 68);
+        assert_eq!(offset(std::ptr::addr_of!(info.vrefresh).cast()), 24);
+        assert_eq!(offset(std::ptr::addr_of!(info.flags).cast()), 28);
+        assert_eq!(offset(std::ptr::addr_of!(info.type_).cast()), 32);
+        assert_eq!(offset(std::ptr::addr_of!(info.name).cast()), 36);
+    }
+}
@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 
 use libdisplay_info_derive::FFIFrom;
 
-use crate::{dmt, ffi, string_from_ffi_ptr, FFIIter};
+use crate::{cvt, dmt, ffi, gtf, mode::Mode, string_from_ffi_ptr, FFIIter};
 
 /// EDID data structure.
 #[derive(Debug)]
@@ -127,6 +127,77 @@ impl Edid<'_> {
         }
     }
 
+    /// Get the timing of an EDID standard timing.
+    ///
+    /// Standard timings with a DMT use it. The others are computed the way
+    /// the Linux kernel does: with CVT for EDID 1.4 displays accepting
+    /// continuous frequencies, otherwise with GTF, using the secondary curve
+    /// of the range limits if there is one. Returns `None` if the display
+    /// supports none of these.
+    pub fn standard_timing_mode(&self, timing: &StandardTimingRef) -> Option<Mode> {
+        if let Some(dmt) = timing.dmt() {
+            return Some(dmt.into());
+        }
+
+        let StandardTiming {
+            horiz_video,
+            refresh_rate_hz,
+            ..
+        } = timing.inner();
+        let vert_video = timing.vert_video();
+        let refresh_rate_hz = refresh_rate_hz as f64;
+        let misc_features = self.misc_features();
+        let range_limits = self
+            .display_descriptors()
+            .iter()
+            .find_map(|descriptor| descriptor.range_limits());
+
+        if self.version() != 1 || self.revision() < 2 {
+            return None;
+        }
+        if self.revision() >= 4 && misc_features.continuous_freq {
+            return Some(
+                cvt::Timing::compute(cvt::Options::new(
+                    cvt::ReducedBlankingVersion::None,
+                    horiz_video,
+                    vert_video,
+                    refresh_rate_hz,
+                ))
+                .into(),
+            );
+        }
+
+        match range_limits {
+            Some(range_limits) if range_limits.type_ == DisplayRangeLimitsType::SecondaryGtf => {
+                Some(
+                    gtf::Timing::compute_with_range_limits(
+                        horiz_video,
+                        vert_video,
+                        refresh_rate_hz,
+                        &range_limits,
+                    )
+                    .into(),
+                )
+            }
+            _ if misc_features.default_gtf => Some(
+                gtf::Timing::compute(gtf::Options {
+                    h_pixels: horiz_video,
+                    v_lines: vert_video,
+                    margins_rqd: false,
+                    ip_param: gtf::IpParam::VFrameRate,
+                    ip_freq_rqd: refresh_rate_hz,
+                    int_rqd: false,
+                    m: gtf::DEFAULT_M,
+                    c: gtf::DEFAULT_C,
+                    k: gtf::DEFAULT_K,
+                    j: gtf::DEFAULT_J,
+                })
+                .into(),
+            ),
+            _ => None,
+        }
+    }
+
     // Get a list of EDID detailed timing definitions.
     pub fn detailed_timing_defs(&self) -> impl Iterator<Item = DetailedTimingDef> {
         FFIIter::new(unsafe { ffi::edid::di_edid_get_detailed_timing_defs(self.edid) })
@@ -793,6 +864,48 @@ pub struct CvtTimingCode {
     pub preferred_vertical_rate: CvtTimingCodePreferredVrate,
 }
 
+impl CvtTimingCode {
+    /// Get the number of addressable pixels per line.
+    ///
+    /// It is derived from the line count and the aspect ratio, rounded down
+    /// to a multiple of the 8 pixel character cell.
+    pub fn horiz_video(&self) -> i32 {
+        let (width, height) = match self.aspect_ratio {
+            CvtTimingCodeAspectRatio::_4_3 => (4, 3),
+            CvtTimingCodeAspectRatio::_16_9 => (16, 9),
+            CvtTimingCodeAspectRatio::_16_10 => (16, 10),
+            CvtTimingCodeAspectRatio::_15_9 => (15, 9),
+        };
+        self.addressable_lines_per_field * width / height / 8 * 8
+    }
+
+    /// Compute the timings of all supported vertical rates.
+    ///
+    /// The standard blanking timings come first, by increasing rate, followed
+    /// by the 60 Hz reduced blanking timing.
+    pub fn timings(&self) -> Vec<cvt::Timing> {
+        let standard = cvt::ReducedBlankingVersion::None;
+        [
+            (self.supports_50hz_sb, 50.0, standard),
+            (self.supports_60hz_sb, 60.0, standard),
+            (self.supports_75hz_sb, 75.0, standard),
+            (self.supports_85hz_sb, 85.0, standard),
+            (self.supports_60hz_rb, 60.0, cvt::ReducedBlankingVersion::V1),
+        ]
+        .into_iter()
+        .filter(|(supported, _, _)| *supported)
+        .map(|(_, refresh_rate_hz, red_blank_ver)| {
+            cvt::Timing::compute(cvt::Options::new(
+                red_blank_ver,
+                self.horiz_video(),
+                self.addressable_lines_per_field,
+                refresh_rate_hz,
+            ))
+        })
+        .collect()
+    }
+}
+
 /// EDID extension block tags, defined in section 2.2.4.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FFIFrom)]
 #[ffi(ffi::edid::di_edid_ext_tag)]
@@ -824,3 +937,936 @@ impl ExtensionRef {
         self.0
     }
 }
+
+/// Encoding an EDID structure failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EncodeError {
+    /// A field holds a value which can't be represented in the encoded structure
+    #[error("{0} is out of range")]
+    OutOfRange(&'static str),
+    /// A string is too long or contains non-ASCII characters
+    #[error("string {0:?} doesn't fit in a display descriptor")]
+    InvalidString(String),
+    /// More items were added than the structure can hold
+    #[error("too many {0}")]
+    TooMany(&'static str),
+    /// The encoded data doesn't fit in the block
+    #[error("not enough space left in the block")]
+    BlockFull,
+    /// No detailed timing definition was added for the preferred timing
+    #[error("no detailed timing definition for the preferred timing")]
+    NoPreferredTiming,
+}
+
+/// Compute the checksum byte which makes all bytes of `data` sum up to zero.
+pub(crate) fn checksum(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg()
+}
+
+/// Check that `value` lies within `min..=max`, and convert it to the target type.
+pub(crate) fn encode_range<T: TryFrom<i64>>(
+    value: impl Into<i64>,
+    min: i64,
+    max: i64,
+    field: &'static str,
+) -> Result<T, EncodeError> {
+    let value = value.into();
+    if value < min || value > max {
+        return Err(EncodeError::OutOfRange(field));
+    }
+    T::try_from(value).map_err(|_| EncodeError::OutOfRange(field))
+}
+
+/// Encode a chromaticity coordinate as a 10 bit binary fraction.
+fn encode_chromaticity(value: f32, field: &'static str) -> Result<u16, EncodeError> {
+    encode_range((value as f64 * 1024.0).round() as i64, 0, 0x3ff, field)
+}
+
+pub(crate) const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// Display descriptor with a dummy tag, used to fill unused descriptor slots.
+pub(crate) const DUMMY_DESCRIPTOR: [u8; 18] =
+    [0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+impl VendorProduct {
+    fn encode(&self) -> Result<[u8; 10], EncodeError> {
+        let mut data = [0u8; 10];
+
+        let mut id = 0u16;
+        for c in self.manufacturer {
+            if !c.is_ascii_uppercase() {
+                return Err(EncodeError::OutOfRange("manufacturer"));
+            }
+            id = id << 5 | (c as u16 - 'A' as u16 + 1);
+        }
+        data[0..2].copy_from_slice(&id.to_be_bytes());
+        data[2..4].copy_from_slice(&self.product.to_le_bytes());
+        data[4..8].copy_from_slice(&self.serial.unwrap_or(0).to_le_bytes());
+
+        if let Some(model_year) = self.model_year {
+            data[8] = 0xff;
+            data[9] = encode_range(model_year - 1990, 0, 0xff, "model_year")?;
+        } else {
+            data[8] = encode_range(self.manufacture_week, 0, 54, "manufacture_week")?;
+            data[9] = encode_range(self.manufacture_year - 1990, 0, 0xff, "manufacture_year")?;
+        }
+
+        Ok(data)
+    }
+}
+
+impl VideoInputAnalog {
+    fn encode(&self) -> u8 {
+        let signal_level_std = match self.signal_level_std {
+            VideoInputAnalogSignalLevelStandard::Level0 => 0,
+            VideoInputAnalogSignalLevelStandard::Level1 => 1,
+            VideoInputAnalogSignalLevelStandard::Level2 => 2,
+            VideoInputAnalogSignalLevelStandard::Level3 => 3,
+        };
+        let video_setup = match self.video_setup {
+            VideoInputAnalogVideoSetup::BlankLevelEqBlack => 0,
+            VideoInputAnalogVideoSetup::BlankToBlackSetupPedestal => 1,
+        };
+
+        signal_level_std << 5
+            | video_setup << 4
+            | (self.sync_separate as u8) << 3
+            | (self.sync_composite as u8) << 2
+            | (self.sync_on_green as u8) << 1
+            | self.sync_serrations as u8
+    }
+}
+
+impl VideoInputDigital {
+    fn encode(&self) -> Result<u8, EncodeError> {
+        let color_bit_depth = match self.color_bit_depth {
+            None => 0,
+            Some(depth @ (6 | 8 | 10 | 12 | 14 | 16)) => (depth as u8 - 4) / 2,
+            Some(_) => return Err(EncodeError::OutOfRange("color_bit_depth")),
+        };
+        let interface = match self.interface {
+            VideoInputDigitalInterface::Undefined => 0,
+            VideoInputDigitalInterface::DVI => 1,
+            VideoInputDigitalInterface::HDMIA => 2,
+            VideoInputDigitalInterface::HDMIB => 3,
+            VideoInputDigitalInterface::MDDI => 4,
+            VideoInputDigitalInterface::DisplayPort => 5,
+        };
+
+        Ok(0x80 | color_bit_depth << 4 | interface)
+    }
+}
+
+impl ScreenSize {
+    fn encode(&self) -> Result<[u8; 2], EncodeError> {
+        if let (Some(width_cm), Some(height_cm)) = (self.width_cm, self.height_cm) {
+            Ok([
+                encode_range(width_cm, 1, 0xff, "width_cm")?,
+                encode_range(height_cm, 1, 0xff, "height_cm")?,
+            ])
+        } else if let Some(aspect_ratio) = self.landscape_aspect_ratio {
+            let value = (aspect_ratio as f64 * 100.0).round() as i64 - 99;
+            Ok([encode_range(value, 1, 0xff, "landscape_aspect_ratio")?, 0])
+        } else if let Some(aspect_ratio) = self.portait_aspect_ratio {
+            let value = (100.0 / aspect_ratio as f64).round() as i64 - 99;
+            Ok([0, encode_range(value, 1, 0xff, "portait_aspect_ratio")?])
+        } else {
+            Ok([0, 0])
+        }
+    }
+}
+
+impl ChromaticityCoords {
+    fn encode(&self) -> Result<[u8; 10], EncodeError> {
+        let coords = [
+            encode_chromaticity(self.red_x, "red_x")?,
+            encode_chromaticity(self.red_y, "red_y")?,
+            encode_chromaticity(self.green_x, "green_x")?,
+            encode_chromaticity(self.green_y, "green_y")?,
+            encode_chromaticity(self.blue_x, "blue_x")?,
+            encode_chromaticity(self.blue_y, "blue_y")?,
+            encode_chromaticity(self.white_x, "white_x")?,
+            encode_chromaticity(self.white_y, "white_y")?,
+        ];
+
+        let mut data = [0u8; 10];
+        for (i, coord) in coords.iter().enumerate() {
+            data[i / 4] |= ((coord & 0x3) as u8) << (6 - 2 * (i % 4));
+            data[2 + i] = (coord >> 2) as u8;
+        }
+        Ok(data)
+    }
+}
+
+impl EstablishedTimings {
+    fn encode(&self) -> [u8; 3] {
+        let bits = [
+            self.has_720x400_70hz,
+            self.has_720x400_88hz,
+            self.has_640x480_60hz,
+            self.has_640x480_67hz,
+            self.has_640x480_72hz,
+            self.has_640x480_75hz,
+            self.has_800x600_56hz,
+            self.has_800x600_60hz,
+            self.has_800x600_72hz,
+            self.has_800x600_75hz,
+            self.has_832x624_75hz,
+            self.has_1024x768_87hz_interlaced,
+            self.has_1024x768_60hz,
+            self.has_1024x768_70hz,
+            self.has_1024x768_75hz,
+            self.has_1280x1024_75hz,
+            self.has_1152x870_75hz,
+        ];
+
+        let mut data = [0u8; 3];
+        for (i, bit) in bits.into_iter().enumerate() {
+            data[i / 8] |= (bit as u8) << (7 - i % 8);
+        }
+        data
+    }
+}
+
+impl StandardTiming {
+    fn encode(&self) -> Result<[u8; 2], EncodeError> {
+        if self.horiz_video % 8 != 0 {
+            return Err(EncodeError::OutOfRange("horiz_video"));
+        }
+        let aspect_ratio = match self.aspect_ratio {
+            StandardTimingAspectRatio::_16_10 => 0,
+            StandardTimingAspectRatio::_4_3 => 1,
+            StandardTimingAspectRatio::_5_4 => 2,
+            StandardTimingAspectRatio::_16_9 => 3,
+        };
+        let refresh_rate: u8 = encode_range(self.refresh_rate_hz - 60, 0, 0x3f, "refresh_rate_hz")?;
+
+        Ok([
+            encode_range(self.horiz_video / 8 - 31, 1, 0xff, "horiz_video")?,
+            aspect_ratio << 6 | refresh_rate,
+        ])
+    }
+}
+
+impl DetailedTimingDef {
+    pub(crate) fn encode(&self) -> Result<[u8; 18], EncodeError> {
+        if self.pixel_clock_hz % 10_000 != 0 {
+            return Err(EncodeError::OutOfRange("pixel_clock_hz"));
+        }
+        let pixel_clock: u16 =
+            encode_range(self.pixel_clock_hz / 10_000, 1, 0xffff, "pixel_clock_hz")?;
+        let horiz_video: u16 = encode_range(self.horiz_video, 0, 0xfff, "horiz_video")?;
+        let horiz_blank: u16 = encode_range(self.horiz_blank, 0, 0xfff, "horiz_blank")?;
+        let vert_video: u16 = encode_range(self.vert_video, 0, 0xfff, "vert_video")?;
+        let vert_blank: u16 = encode_range(self.vert_blank, 0, 0xfff, "vert_blank")?;
+        let horiz_front_porch: u16 =
+            encode_range(self.horiz_front_porch, 0, 0x3ff, "horiz_front_porch")?;
+        let horiz_sync_pulse: u16 =
+            encode_range(self.horiz_sync_pulse, 0, 0x3ff, "horiz_sync_pulse")?;
+        let vert_front_porch: u8 =
+            encode_range(self.vert_front_porch, 0, 0x3f, "vert_front_porch")?;
+        let vert_sync_pulse: u8 = encode_range(self.vert_sync_pulse, 0, 0x3f, "vert_sync_pulse")?;
+        let horiz_image_mm: u16 = encode_range(self.horiz_image_mm, 0, 0xfff, "horiz_image_mm")?;
+        let vert_image_mm: u16 = encode_range(self.vert_image_mm, 0, 0xfff, "vert_image_mm")?;
+
+        let stereo = match self.stereo {
+            DetailedTimingDefStereo::NONE => 0b000,
+            DetailedTimingDefStereo::FieldSeqRight => 0b010,
+            DetailedTimingDefStereo::FieldSeqLeft => 0b100,
+            DetailedTimingDefStereo::TwoWayInterleavedRight => 0b011,
+            DetailedTimingDefStereo::TwoWayInterleavedLeft => 0b101,
+            DetailedTimingDefStereo::FourWayInterleaved => 0b110,
+            DetailedTimingDefStereo::SideBySideInterleaved => 0b111,
+        };
+        let positive = |polarity| (polarity == DetailedTimingDefSyncPolarity::Positive) as u8;
+        let signal = match self.signal_type {
+            DetailedTimingDefSignalType::AnalogComposite => {
+                let flags = self.analog_composite;
+                (flags.is_some_and(|f| f.sync_serrations) as u8) << 2
+                    | (!flags.is_some_and(|f| f.sync_on_green) as u8) << 1
+            }
+            DetailedTimingDefSignalType::BipolarAnalogComposite => {
+                let flags = self.bipolar_analog_composite;
+                0b01 << 3
+                    | (flags.is_some_and(|f| f.sync_serrations) as u8) << 2
+                    | (!flags.is_some_and(|f| f.sync_on_green) as u8) << 1
+            }
+            DetailedTimingDefSignalType::DigitalComposite => {
+                let flags = self.digital_composite;
+                0b10 << 3
+                    | (flags.is_some_and(|f| f.sync_serrations) as u8) << 2
+                    | flags.map_or(0, |f| positive(f.sync_horiz_polarity)) << 1
+            }
+            DetailedTimingDefSignalType::DigitalSeparate => {
+                let flags = self.digital_separate;
+                0b11 << 3
+                    | flags.map_or(0, |f| positive(f.sync_vert_polarity)) << 2
+                    | flags.map_or(0, |f| positive(f.sync_horiz_polarity)) << 1
+            }
+        };
+
+        let mut data = [0u8; 18];
+        data[0..2].copy_from_slice(&pixel_clock.to_le_bytes());
+        data[2] = horiz_video as u8;
+        data[3] = horiz_blank as u8;
+        data[4] = ((horiz_video >> 8) << 4 | horiz_blank >> 8) as u8;
+        data[5] = vert_video as u8;
+        data[6] = vert_blank as u8;
+        data[7] = ((vert_video >> 8) << 4 | vert_blank >> 8) as u8;
+        data[8] = horiz_front_porch as u8;
+        data[9] = horiz_sync_pulse as u8;
+        data[10] = (vert_front_porch & 0xf) << 4 | (vert_sync_pulse & 0xf);
+        data[11] = ((horiz_front_porch >> 8) << 6) as u8
+            | ((horiz_sync_pulse >> 8) << 4) as u8
+            | (vert_front_porch >> 4) << 2
+            | vert_sync_pulse >> 4;
+        data[12] = horiz_image_mm as u8;
+        data[13] = vert_image_mm as u8;
+        data[14] = ((horiz_image_mm >> 8) << 4 | vert_image_mm >> 8) as u8;
+        data[15] = encode_range(self.horiz_border.unwrap_or(0), 0, 0xff, "horiz_border")?;
+        data[16] = encode_range(self.vert_border.unwrap_or(0), 0, 0xff, "vert_border")?;
+        data[17] = (self.interlaced as u8) << 7 | (stereo >> 1) << 5 | signal | stereo & 0b1;
+
+        Ok(data)
+    }
+}
+
+/// Encode a display descriptor header.
+fn descriptor(tag: u8) -> [u8; 18] {
+    let mut data = [0u8; 18];
+    data[3] = tag;
+    data
+}
+
+/// Encode a product serial, data string or product name display descriptor.
+pub(crate) fn string_descriptor(tag: u8, value: &str) -> Result<[u8; 18], EncodeError> {
+    if value.len() > 13 || !value.is_ascii() {
+        return Err(EncodeError::InvalidString(value.to_string()));
+    }
+
+    let mut data = descriptor(tag);
+    data[5..].fill(b' ');
+    data[5..5 + value.len()].copy_from_slice(value.as_bytes());
+    if value.len() < 13 {
+        data[5 + value.len()] = b'\n';
+    }
+    Ok(data)
+}
+
+impl DisplayRangeLimits {
+    pub(crate) fn encode(&self) -> Result<[u8; 18], EncodeError> {
+        let mut data = descriptor(0xfd);
+
+        // Rates above 255 are encoded with an offset of 255.
+        let mut rate = |value: i32, offset_flag: u8, field| -> Result<u8, EncodeError> {
+            let value = encode_range::<u16>(value, 1, 510, field)?;
+            if value > 0xff {
+                data[4] |= offset_flag;
+                Ok((value - 0xff) as u8)
+            } else {
+                Ok(value as u8)
+            }
+        };
+        let min_vert_rate = rate(self.min_vert_rate_hz, 0b0011, "min_vert_rate_hz")?;
+        let max_vert_rate = rate(self.max_vert_rate_hz, 0b0010, "max_vert_rate_hz")?;
+        let min_horiz_rate = rate(self.min_horiz_rate_hz / 1000, 0b1100, "min_horiz_rate_hz")?;
+        let max_horiz_rate = rate(self.max_horiz_rate_hz / 1000, 0b1000, "max_horiz_rate_hz")?;
+        data[5] = min_vert_rate;
+        data[6] = max_vert_rate;
+        data[7] = min_horiz_rate;
+        data[8] = max_horiz_rate;
+
+        let max_pixel_clock_hz = self.max_pixel_clock_hz.unwrap_or(0);
+        data[9] = encode_range(
+            (max_pixel_clock_hz + 9_999_999) / 10_000_000,
+            0,
+            0xff,
+            "max_pixel_clock_hz",
+        )?;
+
+        match self.type_ {
+            DisplayRangeLimitsType::DefaultGtf => data[10] = 0x00,
+            DisplayRangeLimitsType::Bare => data[10] = 0x01,
+            DisplayRangeLimitsType::SecondaryGtf => data[10] = 0x02,
+            DisplayRangeLimitsType::Cvt => data[10] = 0x04,
+        }
+
+        match (self.type_, self.secondary_gtf, self.cvt) {
+            (DisplayRangeLimitsType::SecondaryGtf, Some(gtf), _) => {
+                data[12] = encode_range(gtf.start_freq_hz / 2000, 0, 0xff, "start_freq_hz")?;
+                data[13] = encode_range((gtf.c * 2.0).round() as i64, 0, 0xff, "c")?;
+                let m: u16 = encode_range(gtf.m.round() as i64, 0, 0xffff, "m")?;
+                data[14..16].copy_from_slice(&m.to_le_bytes());
+                data[16] = encode_range(gtf.k.round() as i64, 0, 0xff, "k")?;
+                data[17] = encode_range((gtf.j * 2.0).round() as i64, 0, 0xff, "j")?;
+            }
+            (DisplayRangeLimitsType::Cvt, _, Some(cvt)) => {
+                let version: u8 = encode_range(cvt.version, 0, 0xf, "version")?;
+                let revision: u8 = encode_range(cvt.revision, 0, 0xf, "revision")?;
+                data[11] = version << 4 | revision;
+
+                // The maximum pixel clock is rounded up to 10 MHz above, the
+                // additional precision is subtracted in 0.25 MHz steps.
+                let precision = (data[9] as i64 * 10_000_000 - max_pixel_clock_hz) / 250_000;
+                let max_horiz_px: u16 =
+                    encode_range(cvt.max_horiz_px.unwrap_or(0) / 8, 0, 0x3ff, "max_horiz_px")?;
+                data[12] = (precision as u8) << 2 | (max_horiz_px >> 8) as u8;
+                data[13] = max_horiz_px as u8;
+
+                data[14] = encode_cvt_aspect_ratio(cvt.supported_aspect_ratio);
+                let preferred_aspect_ratio = match cvt.preferred_aspect_ratio {
+                    CvtAspectRatio::_4_3 => 0,
+                    CvtAspectRatio::_16_9 => 1,
+                    CvtAspectRatio::_16_10 => 2,
+                    CvtAspectRatio::_5_4 => 3,
+                    CvtAspectRatio::_15_9 => 4,
+                    _ => return Err(EncodeError::OutOfRange("preferred_aspect_ratio")),
+                };
+                data[15] = preferred_aspect_ratio << 5
+                    | (cvt.reduced_blanking as u8) << 4
+                    | (cvt.standard_blanking as u8) << 3;
+
+                let scaling = cvt.supported_scaling;
+                data[16] = (scaling.contains(CvtScaling::HorizShrink) as u8) << 7
+                    | (scaling.contains(CvtScaling::HorizStretch) as u8) << 6
+                    | (scaling.contains(CvtScaling::VertShrink) as u8) << 5
+                    | (scaling.contains(CvtScaling::VertStretch) as u8) << 4;
+                data[17] = encode_range(
+                    cvt.preferred_vert_refresh_hz,
+                    0,
+                    0xff,
+                    "preferred_vert_refresh_hz",
+                )?;
+            }
+            (DisplayRangeLimitsType::SecondaryGtf, None, _) => {
+                return Err(EncodeError::OutOfRange("secondary_gtf"))
+            }
+            (DisplayRangeLimitsType::Cvt, _, None) => return Err(EncodeError::OutOfRange("cvt")),
+            _ => {
+                data[11] = b'\n';
+                data[12..].fill(b' ');
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+fn color_point_descriptor(color_points: &[ColorPoint]) -> Result<[u8; 18], EncodeError> {
+    if color_points.is_empty() || color_points.len() > 2 {
+        return Err(EncodeError::TooMany("color points"));
+    }
+
+    let mut data = descriptor(0xfb);
+    for (i, color_point) in color_points.iter().enumerate() {
+        data[5 + 5 * i..10 + 5 * i].copy_from_slice(&color_point.encode()?);
+    }
+    data[15] = b'\n';
+    data[16..].fill(b' ');
+    Ok(data)
+}
+
+fn cvt_timing_codes_descriptor(codes: &[CvtTimingCode]) -> Result<[u8; 18], EncodeError> {
+    if codes.len() > 4 {
+        return Err(EncodeError::TooMany("CVT timing codes"));
+    }
+
+    let mut data = descriptor(0xf8);
+    data[5] = 0x01;
+    for (i, code) in codes.iter().enumerate() {
+        data[6 + 3 * i..9 + 3 * i].copy_from_slice(&code.encode()?);
+    }
+    Ok(data)
+}
+
+fn encode_cvt_aspect_ratio(aspect_ratio: CvtAspectRatio) -> u8 {
+    (aspect_ratio.contains(CvtAspectRatio::_4_3) as u8) << 7
+        | (aspect_ratio.contains(CvtAspectRatio::_16_9) as u8) << 6
+        | (aspect_ratio.contains(CvtAspectRatio::_16_10) as u8) << 5
+        | (aspect_ratio.contains(CvtAspectRatio::_5_4) as u8) << 4
+        | (aspect_ratio.contains(CvtAspectRatio::_15_9) as u8) << 3
+}
+
+impl ColorPoint {
+    fn encode(&self) -> Result<[u8; 5], EncodeError> {
+        let white_x = encode_chromaticity(self.white_x, "white_x")?;
+        let white_y = encode_chromaticity(self.white_y, "white_y")?;
+        let gamma = match self.gamma {
+            Some(gamma) => encode_range(
+                (gamma as f64 * 100.0).round() as i64 - 100,
+                0,
+                0xfe,
+                "gamma",
+            )?,
+            None => 0xff,
+        };
+
+        Ok([
+            encode_range(self.index, 1, 0xff, "index")?,
+            ((white_x & 0x3) << 2 | (white_y & 0x3)) as u8,
+            (white_x >> 2) as u8,
+            (white_y >> 2) as u8,
+            gamma,
+        ])
+    }
+}
+
+impl CvtTimingCode {
+    fn encode(&self) -> Result<[u8; 3], EncodeError> {
+        let lines: u16 = encode_range(
+            self.addressable_lines_per_field / 2 - 1,
+            0,
+            0xfff,
+            "addressable_lines_per_field",
+        )?;
+        let aspect_ratio = match self.aspect_ratio {
+            CvtTimingCodeAspectRatio::_4_3 => 0,
+            CvtTimingCodeAspectRatio::_16_9 => 1,
+            CvtTimingCodeAspectRatio::_16_10 => 2,
+            CvtTimingCodeAspectRatio::_15_9 => 3,
+        };
+        let preferred_vertical_rate = match self.preferred_vertical_rate {
+            CvtTimingCodePreferredVrate::_50HZ => 0,
+            CvtTimingCodePreferredVrate::_60HZ => 1,
+            CvtTimingCodePreferredVrate::_75HZ => 2,
+            CvtTimingCodePreferredVrate::_85HZ => 3,
+        };
+
+        Ok([
+            lines as u8,
+            ((lines >> 8) << 4) as u8 | aspect_ratio << 2,
+            preferred_vertical_rate << 5
+                | (self.supports_50hz_sb as u8) << 4
+                | (self.supports_60hz_sb as u8) << 3
+                | (self.supports_75hz_sb as u8) << 2
+                | (self.supports_85hz_sb as u8) << 1
+                | self.supports_60hz_rb as u8,
+        ])
+    }
+}
+
+/// Video input definition of an EDID base block.
+#[derive(Debug, Copy, Clone)]
+enum VideoInput {
+    Analog(VideoInputAnalog),
+    Digital(VideoInputDigital),
+}
+
+/// Builder for an EDID 1.4 base block.
+///
+/// Display descriptors and detailed timing definitions share the four 18-byte
+/// descriptor slots of the base block. The first detailed timing definition
+/// added is the preferred timing and always takes the first slot, as required
+/// by EDID 1.4. The other descriptors follow in the order they were added.
+/// Unused slots are filled with dummy descriptors.
+#[derive(Debug, Clone)]
+pub struct EdidBuilder {
+    vendor_product: VendorProduct,
+    video_input: VideoInput,
+    screen_size: ScreenSize,
+    gamma: Option<f32>,
+    dpms: Dpms,
+    display_color_type: DisplayColorType,
+    color_encoding_formats: Option<ColorEncodingFormats>,
+    misc_features: MiscFeatures,
+    chromaticity_coords: ChromaticityCoords,
+    established_timings: EstablishedTimings,
+    standard_timings: Vec<StandardTiming>,
+    descriptors: Vec<Result<[u8; 18], EncodeError>>,
+    extension_count: u8,
+}
+
+impl EdidBuilder {
+    /// Create a new builder for a digital display with the given vendor &
+    /// product identification.
+    pub fn new(vendor_product: VendorProduct) -> Self {
+        Self {
+            vendor_product,
+            video_input: VideoInput::Digital(VideoInputDigital {
+                dfp1: false,
+                color_bit_depth: None,
+                interface: VideoInputDigitalInterface::Undefined,
+            }),
+            screen_size: ScreenSize {
+                width_cm: None,
+                height_cm: None,
+                landscape_aspect_ratio: None,
+                portait_aspect_ratio: None,
+            },
+            gamma: None,
+            dpms: Dpms {
+                standby: false,
+                suspend: false,
+                off: false,
+            },
+            display_color_type: DisplayColorType::RGB,
+            color_encoding_formats: None,
+            misc_features: MiscFeatures {
+                has_preferred_timing: true,
+                default_gtf: false,
+                srgb_is_primary: false,
+                preferred_timing_is_native: false,
+                continuous_freq: false,
+            },
+            chromaticity_coords: ChromaticityCoords {
+                red_x: 0.0,
+                red_y: 0.0,
+                green_x: 0.0,
+                green_y: 0.0,
+                blue_x: 0.0,
+                blue_y: 0.0,
+                white_x: 0.0,
+                white_y: 0.0,
+            },
+            established_timings: EstablishedTimings {
+                has_720x400_70hz: false,
+                has_720x400_88hz: false,
+                has_640x480_60hz: false,
+                has_640x480_67hz: false,
+                has_640x480_72hz: false,
+                has_640x480_75hz: false,
+                has_800x600_56hz: false,
+                has_800x600_60hz: false,
+                has_800x600_72hz: false,
+                has_800x600_75hz: false,
+                has_832x624_75hz: false,
+                has_1024x768_87hz_interlaced: false,
+                has_1024x768_60hz: false,
+                has_1024x768_70hz: false,
+                has_1024x768_75hz: false,
+                has_1280x1024_75hz: false,
+                has_1152x870_75hz: false,
+            },
+            standard_timings: Vec::new(),
+            descriptors: Vec::new(),
+            extension_count: 0,
+        }
+    }
+
+    /// Set the vendor & product identification.
+    pub fn vendor_product(mut self, vendor_product: VendorProduct) -> Self {
+        self.vendor_product = vendor_product;
+        self
+    }
+
+    /// Describe the display as analog.
+    pub fn video_input_analog(mut self, video_input: VideoInputAnalog) -> Self {
+        self.video_input = VideoInput::Analog(video_input);
+        self
+    }
+
+    /// Describe the display as digital.
+    ///
+    /// [`VideoInputDigital::dfp1`] only exists in EDID 1.3 and is ignored.
+    pub fn video_input_digital(mut self, video_input: VideoInputDigital) -> Self {
+        self.video_input = VideoInput::Digital(video_input);
+        self
+    }
+
+    /// Set the screen size or aspect ratio.
+    pub fn screen_size(mut self, screen_size: ScreenSize) -> Self {
+        self.screen_size = screen_size;
+        self
+    }
+
+    /// Set the display transfer characteristics, also known as "gamma".
+    ///
+    /// `None` means the gamma is stored in an extension block.
+    pub fn gamma(mut self, gamma: Option<f32>) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Set the supported legacy DPMS states.
+    pub fn dpms(mut self, dpms: Dpms) -> Self {
+        self.dpms = dpms;
+        self
+    }
+
+    /// Set the display color type.
+    ///
+    /// Only used for analog displays.
+    pub fn display_color_type(mut self, display_color_type: DisplayColorType) -> Self {
+        self.display_color_type = display_color_type;
+        self
+    }
+
+    /// Set the supported color encoding formats.
+    ///
+    /// Only used for digital displays. RGB 4:4:4 is always supported.
+    pub fn color_encoding_formats(mut self, formats: ColorEncodingFormats) -> Self {
+        self.color_encoding_formats = Some(formats);
+        self
+    }
+
+    /// Set the miscellaneous basic features.
+    ///
+    /// [`MiscFeatures::has_preferred_timing`] and [`MiscFeatures::default_gtf`]
+    /// don't exist in EDID 1.4 and are ignored.
+    pub fn misc_features(mut self, misc_features: MiscFeatures) -> Self {
+        self.misc_features = misc_features;
+        self
+    }
+
+    /// Set the chromaticity coordinates.
+    pub fn chromaticity_coords(mut self, coords: ChromaticityCoords) -> Self {
+        self.chromaticity_coords = coords;
+        self
+    }
+
+    /// Set the established timings I and II.
+    pub fn established_timings(mut self, timings: EstablishedTimings) -> Self {
+        self.established_timings = timings;
+        self
+    }
+
+    /// Add a standard timing.
+    ///
+    /// At most 8 standard timings fit in the base block.
+    pub fn standard_timing(mut self, timing: StandardTiming) -> Self {
+        self.standard_timings.push(timing);
+        self
+    }
+
+    /// Add a detailed timing definition.
+    pub fn detailed_timing_def(mut self, timing: DetailedTimingDef) -> Self {
+        self.descriptors.push(timing.encode());
+        self
+    }
+
+    /// Add a product name display descriptor.
+    ///
+    /// The name is limited to 13 ASCII characters.
+    pub fn product_name(mut self, name: &str) -> Self {
+        self.descriptors.push(string_descriptor(0xfc, name));
+        self
+    }
+
+    /// Add a product serial display descriptor.
+    ///
+    /// The serial is limited to 13 ASCII characters.
+    pub fn product_serial(mut self, serial: &str) -> Self {
+        self.descriptors.push(string_descriptor(0xff, serial));
+        self
+    }
+
+    /// Add a data string display descriptor.
+    ///
+    /// The string is limited to 13 ASCII characters.
+    pub fn data_string(mut self, string: &str) -> Self {
+        self.descriptors.push(string_descriptor(0xfe, string));
+        self
+    }
+
+    /// Add a display range limits descriptor.
+    pub fn range_limits(mut self, range_limits: DisplayRangeLimits) -> Self {
+        self.descriptors.push(range_limits.encode());
+        self
+    }
+
+    /// Add a color point descriptor, containing one or two color points.
+    pub fn color_points(mut self, color_points: &[ColorPoint]) -> Self {
+        self.descriptors.push(color_point_descriptor(color_points));
+        self
+    }
+
+    /// Add a CVT timing codes descriptor, containing up to 4 codes.
+    ///
+    /// The highest priority code comes first.
+    pub fn cvt_timing_codes(mut self, codes: &[CvtTimingCode]) -> Self {
+        self.descriptors.push(cvt_timing_codes_descriptor(codes));
+        self
+    }
+
+    /// Set the number of extension blocks following the base block.
+    pub fn extension_count(mut self, count: u8) -> Self {
+        self.extension_count = count;
+        self
+    }
+
+    /// Encode the base block.
+    pub fn build(&self) -> Result<[u8; 128], EncodeError> {
+        let mut data = [0u8; 128];
+
+        data[0..8].copy_from_slice(&EDID_HEADER);
+        data[8..18].copy_from_slice(&self.vendor_product.encode()?);
+        data[18] = 1;
+        data[19] = 4;
+
+        data[20] = match &self.video_input {
+            VideoInput::Analog(analog) => analog.encode(),
+            VideoInput::Digital(digital) => digital.encode()?,
+        };
+        data[21..23].copy_from_slice(&self.screen_size.encode()?);
+        data[23] = match self.gamma {
+            Some(gamma) => encode_range(
+                (gamma as f64 * 100.0).round() as i64 - 100,
+                0,
+                0xfe,
+                "gamma",
+            )?,
+            None => 0xff,
+        };
+
+        let color = match &self.video_input {
+            VideoInput::Analog(_) => match self.display_color_type {
+                DisplayColorType::Monochrome => 0,
+                DisplayColorType::RGB => 1,
+                DisplayColorType::NonRGB => 2,
+                DisplayColorType::Undefined => 3,
+            },
+            VideoInput::Digital(_) => self.color_encoding_formats.map_or(0, |formats| {
+                (formats.ycrcb422 as u8) << 1 | formats.ycrcb444 as u8
+            }),
+        };
+        data[24] = (self.dpms.standby as u8) << 7
+            | (self.dpms.suspend as u8) << 6
+            | (self.dpms.off as u8) << 5
+            | color << 3
+            | (self.misc_features.srgb_is_primary as u8) << 2
+            | (self.misc_features.preferred_timing_is_native as u8) << 1
+            | self.misc_features.continuous_freq as u8;
+
+        data[25..35].copy_from_slice(&self.chromaticity_coords.encode()?);
+        data[35..38].copy_from_slice(&self.established_timings.encode());
+
+        if self.standard_timings.len() > 8 {
+            return Err(EncodeError::TooMany("standard timings"));
+        }
+        data[38..54].fill(0x01);
+        for (i, timing) in self.standard_timings.iter().enumerate() {
+            data[38 + 2 * i..40 + 2 * i].copy_from_slice(&timing.encode()?);
+        }
+
+        if self.descriptors.len() > 4 {
+            return Err(EncodeError::TooMany("descriptors"));
+        }
+        let mut descriptors = self
+            .descriptors
+            .iter()
+            .cloned()
+            .collect::<Result<Vec<_>, _>>()?;
+        // Detailed timing definitions start with a non-zero pixel clock.
+        let preferred = descriptors
+            .iter()
+            .position(|descriptor| descriptor[0..2] != [0, 0])
+            .ok_or(EncodeError::NoPreferredTiming)?;
+        descriptors[..=preferred].rotate_right(1);
+        descriptors.resize(4, DUMMY_DESCRIPTOR);
+        for (i, descriptor) in descriptors.iter().enumerate() {
+            data[54 + 18 * i..72 + 18 * i].copy_from_slice(descriptor);
+        }
+
+        data[126] = self.extension_count;
+        data[127] = checksum(&data[..127]);
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Detailed timing definition of 1920x1080@60, CTA-861 VIC 16.
+    pub(crate) fn dtd_1080p60() -> DetailedTimingDef {
+        DetailedTimingDef {
+            pixel_clock_hz: 148_500_000,
+            horiz_video: 1920,
+            vert_video: 1080,
+            horiz_blank: 280,
+            vert_blank: 45,
+            horiz_front_porch: 88,
+            vert_front_porch: 4,
+            horiz_sync_pulse: 44,
+            vert_sync_pulse: 5,
+            horiz_image_mm: 531,
+            vert_image_mm: 299,
+            horiz_border: None,
+            vert_border: None,
+            interlaced: false,
+            stereo: DetailedTimingDefStereo::NONE,
+            signal_type: DetailedTimingDefSignalType::DigitalSeparate,
+            analog_composite: None,
+            bipolar_analog_composite: None,
+            digital_composite: None,
+            digital_separate: Some(DetailedTimingDigitalSeparate {
+                sync_vert_polarity: DetailedTimingDefSyncPolarity::Positive,
+                sync_horiz_polarity: DetailedTimingDefSyncPolarity::Positive,
+            }),
+        }
+    }
+
+    pub(crate) fn vendor_product() -> VendorProduct {
+        VendorProduct {
+            manufacturer: ['L', 'D', 'I'],
+            product: 0x1234,
+            serial: Some(42),
+            manufacture_week: 10,
+            manufacture_year: 2024,
+            model_year: None,
+        }
+    }
+
+    #[test]
+    fn detailed_timing_def() {
+        assert_eq!(
+            dtd_1080p60().encode().unwrap(),
+            [
+                0x02, 0x3a, 0x80, 0x18, 0x71, 0x38, 0x2d, 0x40, 0x58, 0x2c, 0x45, 0x00, 0x13, 0x2b,
+                0x21, 0x00, 0x00, 0x1e
+            ]
+        );
+    }
+
+    #[test]
+    fn base_block() {
+        let block = EdidBuilder::new(vendor_product())
+            .detailed_timing_def(dtd_1080p60())
+            .product_name("Test")
+            .extension_count(1)
+            .build()
+            .unwrap();
+        assert_eq!(block[..8], EDID_HEADER);
+        assert_eq!(block[8..10], [0x30, 0x89]);
+        assert_eq!((block[18], block[19]), (1, 4));
+        assert_eq!(block[54..72], dtd_1080p60().encode().unwrap());
+        assert_eq!(block[72..90], string_descriptor(0xfc, "Test").unwrap());
+        assert_eq!(block[90..108], DUMMY_DESCRIPTOR);
+        assert_eq!(block[126], 1);
+        assert_eq!(checksum(&block), 0);
+    }
+
+    #[test]
+    fn preferred_timing_takes_the_first_slot() {
+        let block = EdidBuilder::new(vendor_product())
+            .product_name("Test")
+            .product_serial("1234")
+            .detailed_timing_def(dtd_1080p60())
+            .build()
+            .unwrap();
+        assert_eq!(block[54..72], dtd_1080p60().encode().unwrap());
+        assert_eq!(block[72..90], string_descriptor(0xfc, "Test").unwrap());
+        assert_eq!(block[90..108], string_descriptor(0xff, "1234").unwrap());
+        assert_eq!(block[108..126], DUMMY_DESCRIPTOR);
+    }
+
+    #[test]
+    fn encode_errors() {
+        let builder = EdidBuilder::new(vendor_product()).product_name("Test");
+        assert_eq!(builder.build(), Err(EncodeError::NoPreferredTiming));
+
+        let builder = EdidBuilder::new(vendor_product()).product_name("A name which is too long");
+        assert!(matches!(
+            builder.build(),
+            Err(EncodeError::InvalidString(_))
+        ));
+
+        let builder = (0..5).fold(EdidBuilder::new(vendor_product()), |builder, _| {
+            builder.detailed_timing_def(dtd_1080p60())
+        });
+        assert_eq!(builder.build(), Err(EncodeError::TooMany("descriptors")));
+    }
+}
@@ -3,7 +3,10 @@ use std::mem::MaybeUninit;
 
 use libdisplay_info_derive::FFIFrom;
 
-use crate::ffi;
+use crate::{
+    edid::{DisplayRangeLimits, DisplayRangeLimitsType},
+    ffi,
+};
 
 pub const DEFAULT_M: f64 = 600.0;
 pub const DEFAULT_C: f64 = 40.0;
@@ -87,4 +90,54 @@ impl Timing {
         }
         Timing::from(unsafe { timing.assume_init() })
     }
+
+    /// Compute a progressive timing via the GTF formula, with the curve
+    /// selected by the display range limits.
+    ///
+    /// As defined in EDID 1.3, the secondary curve advertised by the range
+    /// limits is used for horizontal frequencies at or above its start break
+    /// frequency, and the default curve below it or when the range limits
+    /// don't advertise a secondary curve.
+    pub fn compute_with_range_limits(
+        h_pixels: i32,
+        v_lines: i32,
+        refresh_hz: f64,
+        range_limits: &DisplayRangeLimits,
+    ) -> Self {
+        let options = Options {
+            h_pixels,
+            v_lines,
+            margins_rqd: false,
+            ip_param: IpParam::VFrameRate,
+            ip_freq_rqd: refresh_hz,
+            int_rqd: false,
+            m: DEFAULT_M,
+            c: DEFAULT_C,
+            k: DEFAULT_K,
+            j: DEFAULT_J,
+        };
+        let timing = Timing::compute(options);
+
+        let secondary = match (range_limits.type_, range_limits.secondary_gtf) {
+            (DisplayRangeLimitsType::SecondaryGtf, Some(secondary)) => secondary,
+            _ => return timing,
+        };
+        let h_total = timing.h_pixels
+            + 2 * timing.h_border
+            + timing.h_front_porch
+            + timing.h_sync
+            + timing.h_back_porch;
+        let h_freq_hz = timing.pixel_freq_mhz * 1_000_000.0 / h_total as f64;
+        if h_freq_hz < secondary.start_freq_hz as f64 {
+            return timing;
+        }
+
+        Timing::compute(Options {
+            m: secondary.m as f64,
+            c: secondary.c as f64,
+            k: secondary.k as f64,
+            j: secondary.j as f64,
+            ..options
+        })
+    }
 }
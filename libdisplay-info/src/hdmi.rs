@@ -0,0 +1,702 @@
+//! HDMI link bandwidth computations.
+//!
+//! HDMI carries video either over TMDS, up to a character rate of 600 MHz, or
+//! over Fixed Rate Link (FRL), introduced in HDMI 2.1, with 3 or 4 lanes of up
+//! to 12 Gbps each. [`check`] finds out how a mode can be carried given the
+//! capabilities of the sink, as advertised in its EDID, and of the source.
+//! Modes which only fit with Display Stream Compression (DSC) can be
+//! configured with [`dsc_config`].
+use crate::{
+    cta::{HdmiDsc, HdmiDscMaxSlices, HdmiFrl, HdmiScds, VendorHdmiBlock},
+    mode::Mode,
+};
+
+pub use crate::mode::PixelEncoding;
+
+/// TMDS character rate supported by all HDMI sinks.
+const DEFAULT_MAX_TMDS_CHAR_RATE_HZ: i64 = 165_000_000;
+
+/// Share of the FRL bandwidth not available for video, once the 16b/18b
+/// coding is accounted for: Reed-Solomon FEC, map characters and packets.
+const FRL_OVERHEAD: f64 = 0.03;
+
+/// FRL rates, from the slowest to the fastest.
+const FRL_RATES: [HdmiFrl; 6] = [
+    HdmiFrl::_3GBps3Lanes,
+    HdmiFrl::_6GBps3Lanes,
+    HdmiFrl::_6GBps4Lanes,
+    HdmiFrl::_8GBps4Lanes,
+    HdmiFrl::_10GBps4Lanes,
+    HdmiFrl::_12GBps4Lanes,
+];
+
+/// Get the number of lanes and the bit rate per lane in Gbps of an FRL rate.
+fn frl_lanes_gbps(frl: HdmiFrl) -> (u32, u32) {
+    match frl {
+        HdmiFrl::Unsupported => (0, 0),
+        HdmiFrl::_3GBps3Lanes => (3, 3),
+        HdmiFrl::_6GBps3Lanes => (3, 6),
+        HdmiFrl::_6GBps4Lanes => (4, 6),
+        HdmiFrl::_8GBps4Lanes => (4, 8),
+        HdmiFrl::_10GBps4Lanes => (4, 10),
+        HdmiFrl::_12GBps4Lanes => (4, 12),
+    }
+}
+
+/// Get the video payload bandwidth of an FRL rate, in bits per second.
+pub(crate) fn frl_capacity_bps(frl: HdmiFrl) -> f64 {
+    let (lanes, gbps) = frl_lanes_gbps(frl);
+    lanes as f64 * gbps as f64 * 1e9 * 16.0 / 18.0 * (1.0 - FRL_OVERHEAD)
+}
+
+/// Get the FRL rates up to and including `max`, from the slowest.
+pub(crate) fn frl_rates_up_to(max: HdmiFrl) -> &'static [HdmiFrl] {
+    let len = FRL_RATES
+        .iter()
+        .position(|frl| *frl == max)
+        .map_or(0, |index| index + 1);
+    &FRL_RATES[..len]
+}
+
+/// Get the slowest of two FRL rates.
+pub(crate) fn min_frl(a: HdmiFrl, b: HdmiFrl) -> HdmiFrl {
+    let index = |frl: HdmiFrl| FRL_RATES.iter().position(|rate| *rate == frl);
+    match (index(a), index(b)) {
+        (Some(a_index), Some(b_index)) if a_index <= b_index => a,
+        (Some(_), Some(_)) => b,
+        _ => HdmiFrl::Unsupported,
+    }
+}
+
+/// HDMI capabilities of a sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkCaps {
+    /// Maximum TMDS character rate in Hz
+    pub max_tmds_char_rate_hz: i64,
+    pub supports_dc_30bit: bool,
+    pub supports_dc_36bit: bool,
+    pub supports_dc_48bit: bool,
+    /// Whether deep color is supported with YCbCr 4:4:4
+    pub supports_dc_y444: bool,
+    pub supports_dc_30bit_420: bool,
+    pub supports_dc_36bit_420: bool,
+    pub supports_dc_48bit_420: bool,
+    pub max_frl_rate: HdmiFrl,
+    /// DSC capabilities, see [`dsc_config`]
+    pub dsc: Option<HdmiDsc>,
+}
+
+impl SinkCaps {
+    /// Collect the capabilities of a sink from its HDMI Vendor-Specific Data
+    /// Block and its HDMI Forum Sink Capability Data Structure.
+    ///
+    /// Without a maximum TMDS clock, sinks are limited to 165 MHz.
+    pub fn new(vendor_hdmi: Option<&VendorHdmiBlock>, scds: Option<&HdmiScds>) -> Self {
+        let max_tmds_char_rate_hz = match (vendor_hdmi, scds) {
+            (_, Some(scds)) if scds.max_tmds_char_rate_mhz > 0 => {
+                scds.max_tmds_char_rate_mhz as i64 * 1_000_000
+            }
+            (Some(vendor_hdmi), _) if vendor_hdmi.max_tmds_clock > 0 => {
+                vendor_hdmi.max_tmds_clock as i64 * 1_000_000
+            }
+            _ => DEFAULT_MAX_TMDS_CHAR_RATE_HZ,
+        };
+
+        SinkCaps {
+            max_tmds_char_rate_hz,
+            supports_dc_30bit: vendor_hdmi.is_some_and(|block| block.supports_dc_30bit),
+            supports_dc_36bit: vendor_hdmi.is_some_and(|block| block.supports_dc_36bit),
+            supports_dc_48bit: vendor_hdmi.is_some_and(|block| block.supports_dc_48bit),
+            supports_dc_y444: vendor_hdmi.is_some_and(|block| block.supports_dc_y444),
+            supports_dc_30bit_420: scds.is_some_and(|scds| scds.supports_dc_30bit_420),
+            supports_dc_36bit_420: scds.is_some_and(|scds| scds.supports_dc_36bit_420),
+            supports_dc_48bit_420: scds.is_some_and(|scds| scds.supports_dc_48bit_420),
+            max_frl_rate: scds.map_or(HdmiFrl::Unsupported, |scds| scds.max_frl_rate),
+            dsc: scds.and_then(|scds| scds.dsc),
+        }
+    }
+
+    /// Check whether the sink accepts a pixel encoding at a bit depth.
+    fn supports_bpc(&self, encoding: PixelEncoding, bpc: u8) -> bool {
+        match (encoding, bpc) {
+            (_, 8) | (PixelEncoding::Ycbcr422, 10 | 12) => true,
+            (PixelEncoding::Ycbcr420, 10) => self.supports_dc_30bit_420,
+            (PixelEncoding::Ycbcr420, 12) => self.supports_dc_36bit_420,
+            (PixelEncoding::Ycbcr420, 16) => self.supports_dc_48bit_420,
+            (PixelEncoding::Ycbcr444, _) if !self.supports_dc_y444 => false,
+            (PixelEncoding::Rgb | PixelEncoding::Ycbcr444, 10) => self.supports_dc_30bit,
+            (PixelEncoding::Rgb | PixelEncoding::Ycbcr444, 12) => self.supports_dc_36bit,
+            (PixelEncoding::Rgb | PixelEncoding::Ycbcr444, 16) => self.supports_dc_48bit,
+            _ => false,
+        }
+    }
+}
+
+/// HDMI capabilities of a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceCaps {
+    /// Maximum TMDS character rate in Hz
+    pub max_tmds_char_rate_hz: i64,
+    pub max_frl_rate: HdmiFrl,
+    pub supports_ycbcr420: bool,
+    pub supports_dsc: bool,
+}
+
+/// Link carrying a mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+    /// TMDS, at the given character rate in Hz
+    Tmds { char_rate_hz: i64 },
+    /// FRL, at the given rate
+    Frl(HdmiFrl),
+}
+
+/// Result of an HDMI bandwidth check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feasibility {
+    /// The mode fits with the requested pixel encoding
+    Fits(Link),
+    /// The mode only fits with YCbCr 4:2:0, at the requested bit depth
+    NeedsYcbcr420(Link),
+    /// The mode only fits with DSC, in the given configuration
+    NeedsDsc(DscConfig),
+    /// The mode can't be carried
+    Unsupported,
+}
+
+/// Get the TMDS character rate of a mode in Hz.
+///
+/// Deep color raises the character rate by the ratio of the bit depth to 8
+/// bits, except for YCbCr 4:2:2 which always uses 24 bits per pixel. YCbCr
+/// 4:2:0 halves the character rate.
+pub fn tmds_char_rate_hz(mode: &Mode, encoding: PixelEncoding, bpc: u8) -> i64 {
+    match encoding {
+        PixelEncoding::Ycbcr422 => mode.pixel_clock_hz,
+        PixelEncoding::Ycbcr420 => mode.pixel_clock_hz * bpc as i64 / 16,
+        PixelEncoding::Rgb | PixelEncoding::Ycbcr444 => mode.pixel_clock_hz * bpc as i64 / 8,
+    }
+}
+
+/// Get the rate of the active pixels of a mode in Hz.
+///
+/// FRL transmits the active pixels of a line, the blanking only carries
+/// packets and is compressed.
+pub(crate) fn active_pixel_rate_hz(mode: &Mode) -> f64 {
+    mode.pixel_clock_hz as f64 * mode.h_active as f64 / mode.h_total().max(1) as f64
+}
+
+/// Find the link able to carry a mode without compression.
+fn find_link(
+    mode: &Mode,
+    encoding: PixelEncoding,
+    bpc: u8,
+    sink: &SinkCaps,
+    source: &SourceCaps,
+) -> Option<Link> {
+    if !sink.supports_bpc(encoding, bpc) {
+        return None;
+    }
+
+    let char_rate_hz = tmds_char_rate_hz(mode, encoding, bpc);
+    if char_rate_hz <= sink.max_tmds_char_rate_hz.min(source.max_tmds_char_rate_hz) {
+        return Some(Link::Tmds { char_rate_hz });
+    }
+
+    let required_bps = active_pixel_rate_hz(mode) * encoding.bits_per_pixel(bpc);
+    frl_rates_up_to(min_frl(sink.max_frl_rate, source.max_frl_rate))
+        .iter()
+        .find(|frl| frl_capacity_bps(**frl) >= required_bps)
+        .map(|frl| Link::Frl(*frl))
+}
+
+/// Check how a mode can be carried over HDMI.
+///
+/// The link is selected in order of preference: TMDS, then the slowest FRL
+/// rate, first with the requested pixel encoding, then with YCbCr 4:2:0.
+/// The FRL bandwidth is estimated from the active pixels. As a last resort,
+/// the mode is compressed with the configuration of [`dsc_config`].
+pub fn check(
+    mode: &Mode,
+    encoding: PixelEncoding,
+    bpc: u8,
+    sink: &SinkCaps,
+    source: &SourceCaps,
+) -> Feasibility {
+    if let Some(link) = find_link(mode, encoding, bpc, sink, source) {
+        return Feasibility::Fits(link);
+    }
+    if encoding != PixelEncoding::Ycbcr420 && source.supports_ycbcr420 {
+        if let Some(link) = find_link(mode, PixelEncoding::Ycbcr420, bpc, sink, source) {
+            return Feasibility::NeedsYcbcr420(link);
+        }
+    }
+    match sink
+        .dsc
+        .map(|dsc| dsc_config(mode, encoding, bpc, &dsc, source))
+    {
+        Some(Ok(config)) => Feasibility::NeedsDsc(config),
+        _ => Feasibility::Unsupported,
+    }
+}
+
+/// Slice counts allowed by HDMI 2.1, from the fewest.
+const DSC_SLICE_COUNTS: [u32; 6] = [1, 2, 4, 8, 12, 16];
+
+/// Maximum slice width in pixels.
+const DSC_MAX_SLICE_WIDTH: i32 = 2720;
+
+/// DSC configuration of an HDMI link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DscConfig {
+    /// Number of slices per line
+    pub slice_count: u32,
+    /// Target compressed bits per pixel, in units of 1/16 bit
+    pub bits_per_pixel_x16: u32,
+    pub frl_rate: HdmiFrl,
+}
+
+/// No DSC configuration can carry a mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum DscError {
+    /// The sink doesn't support DSC
+    #[error("DSC is not supported")]
+    Unsupported,
+    /// The sink doesn't support DSC with this pixel encoding
+    #[error("DSC is not supported with {0:?}")]
+    UnsupportedEncoding(PixelEncoding),
+    /// The sink doesn't support DSC at this bit depth
+    #[error("DSC is not supported at {0} bits per component")]
+    UnsupportedBpc(u8),
+    /// The pixel clock or the line width requires more slices than supported
+    #[error("the mode requires more slices than supported")]
+    TooFewSlices,
+    /// No FRL rate carries the minimum compressed bit rate
+    #[error("no FRL rate is fast enough")]
+    InsufficientBandwidth,
+}
+
+/// Compute the DSC configuration of a mode.
+///
+/// The configuration uses the fewest slices allowed by the maximum slice
+/// rate and width, and the highest target bits per pixel any FRL rate up to
+/// the maximum of the sink and the source can carry, at the slowest such
+/// rate. The target is limited by the maximum total chunk size, and is a
+/// whole number of bits unless the sink supports all fractional values.
+pub fn dsc_config(
+    mode: &Mode,
+    encoding: PixelEncoding,
+    bpc: u8,
+    dsc: &HdmiDsc,
+    source: &SourceCaps,
+) -> Result<DscConfig, DscError> {
+    let (max_slices, max_slice_rate_hz) = match dsc.max_slices {
+        HdmiDscMaxSlices::Unsupported => return Err(DscError::Unsupported),
+        HdmiDscMaxSlices::_1_340MHZ => (1, 340_000_000),
+        HdmiDscMaxSlices::_2_340MHZ => (2, 340_000_000),
+        HdmiDscMaxSlices::_4_340MHZ => (4, 340_000_000),
+        HdmiDscMaxSlices::_8_340MHZ => (8, 340_000_000),
+        HdmiDscMaxSlices::_8_400MHZ => (8, 400_000_000),
+        HdmiDscMaxSlices::_12_400MHZ => (12, 400_000_000),
+        HdmiDscMaxSlices::_16_400MHZ => (16, 400_000_000),
+    };
+    if !source.supports_dsc {
+        return Err(DscError::Unsupported);
+    }
+
+    let min_bits_per_pixel_x16 = match encoding {
+        PixelEncoding::Rgb | PixelEncoding::Ycbcr444 => 8 * 16,
+        PixelEncoding::Ycbcr420 if dsc.supports_native_420 => 6 * 16,
+        _ => return Err(DscError::UnsupportedEncoding(encoding)),
+    };
+    let supported_bpc = match bpc {
+        8 => true,
+        10 => dsc.supports_10bpc,
+        12 => dsc.supports_12bpc,
+        _ => false,
+    };
+    if !supported_bpc {
+        return Err(DscError::UnsupportedBpc(bpc));
+    }
+
+    let (slice_count, slice_width) = DSC_SLICE_COUNTS
+        .iter()
+        .filter(|slice_count| **slice_count <= max_slices)
+        .map(|slice_count| {
+            let slice_width = (mode.h_active + *slice_count as i32 - 1) / *slice_count as i32;
+            (*slice_count, slice_width)
+        })
+        .find(|(slice_count, slice_width)| {
+            mode.pixel_clock_hz <= max_slice_rate_hz * *slice_count as i64
+                && *slice_width <= DSC_MAX_SLICE_WIDTH
+        })
+        .ok_or(DscError::TooFewSlices)?;
+
+    // The compressed stream has fewer bits per pixel than the uncompressed
+    // one, and each slice of a line is sent as a chunk of whole bytes.
+    let mut max_bits_per_pixel_x16 = (encoding.bits_per_pixel(bpc) * 16.0) as u32 - 1;
+    if dsc.max_total_chunk_bytes > 0 {
+        let chunk_bytes = dsc.max_total_chunk_bytes as u32 / slice_count;
+        max_bits_per_pixel_x16 =
+            max_bits_per_pixel_x16.min(chunk_bytes * 8 * 16 / slice_width.max(1) as u32);
+    }
+
+    let active_pixel_rate_hz = active_pixel_rate_hz(mode);
+    let mut best: Option<DscConfig> = None;
+    for frl_rate in frl_rates_up_to(min_frl(dsc.max_frl_rate, source.max_frl_rate)) {
+        let link_bits_per_pixel_x16 =
+            (frl_capacity_bps(*frl_rate) / active_pixel_rate_hz * 16.0) as u32;
+        let mut bits_per_pixel_x16 = link_bits_per_pixel_x16.min(max_bits_per_pixel_x16);
+        if !dsc.supports_all_bpc {
+            bits_per_pixel_x16 -= bits_per_pixel_x16 % 16;
+        }
+
+        if bits_per_pixel_x16 >= min_bits_per_pixel_x16
+            && best.map_or(true, |best| bits_per_pixel_x16 > best.bits_per_pixel_x16)
+        {
+            best = Some(DscConfig {
+                slice_count,
+                bits_per_pixel_x16,
+                frl_rate: *frl_rate,
+            });
+        }
+    }
+
+    best.ok_or(DscError::InsufficientBandwidth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::SyncPolarity;
+
+    /// A 3840x2160 mode with the CTA-861 timings of VIC 97.
+    fn mode_4k(pixel_clock_hz: i64) -> Mode {
+        Mode {
+            pixel_clock_hz,
+            h_active: 3840,
+            h_front: 176,
+            h_sync: 88,
+            h_back: 296,
+            v_active: 2160,
+            v_front: 8,
+            v_sync: 10,
+            v_back: 72,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    /// A 7680x4320 mode with the CTA-861 timings of VIC 199.
+    fn mode_8k(pixel_clock_hz: i64) -> Mode {
+        Mode {
+            pixel_clock_hz,
+            h_active: 7680,
+            h_front: 352,
+            h_sync: 176,
+            h_back: 592,
+            v_active: 4320,
+            v_front: 16,
+            v_sync: 20,
+            v_back: 144,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    fn sink() -> SinkCaps {
+        SinkCaps {
+            max_tmds_char_rate_hz: 600_000_000,
+            supports_dc_30bit: true,
+            supports_dc_36bit: true,
+            supports_dc_48bit: false,
+            supports_dc_y444: true,
+            supports_dc_30bit_420: true,
+            supports_dc_36bit_420: false,
+            supports_dc_48bit_420: false,
+            max_frl_rate: HdmiFrl::_12GBps4Lanes,
+            dsc: None,
+        }
+    }
+
+    fn source() -> SourceCaps {
+        SourceCaps {
+            max_tmds_char_rate_hz: 600_000_000,
+            max_frl_rate: HdmiFrl::_12GBps4Lanes,
+            supports_ycbcr420: true,
+            supports_dsc: true,
+        }
+    }
+
+    fn hdmi_dsc() -> HdmiDsc {
+        HdmiDsc {
+            supports_10bpc: true,
+            supports_12bpc: false,
+            supports_all_bpc: false,
+            supports_native_420: true,
+            max_slices: HdmiDscMaxSlices::_8_400MHZ,
+            max_frl_rate: HdmiFrl::_12GBps4Lanes,
+            max_total_chunk_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn link_rates() {
+        let mode = mode_4k(594_000_000);
+        assert_eq!(tmds_char_rate_hz(&mode, PixelEncoding::Rgb, 8), 594_000_000);
+        assert_eq!(
+            tmds_char_rate_hz(&mode, PixelEncoding::Rgb, 10),
+            742_500_000
+        );
+        assert_eq!(
+            tmds_char_rate_hz(&mode, PixelEncoding::Ycbcr422, 12),
+            594_000_000
+        );
+        assert_eq!(
+            tmds_char_rate_hz(&mode, PixelEncoding::Ycbcr420, 10),
+            371_250_000
+        );
+
+        assert_eq!(frl_capacity_bps(HdmiFrl::Unsupported), 0.0);
+        assert_eq!(
+            frl_capacity_bps(HdmiFrl::_3GBps3Lanes) as i64 / 1_000_000,
+            7_760
+        );
+        assert_eq!(
+            frl_capacity_bps(HdmiFrl::_12GBps4Lanes) as i64 / 1_000_000,
+            41_386
+        );
+    }
+
+    #[test]
+    fn check_links() {
+        let hdmi_2_0 = SinkCaps {
+            max_frl_rate: HdmiFrl::Unsupported,
+            ..sink()
+        };
+        assert_eq!(
+            check(
+                &mode_4k(594_000_000),
+                PixelEncoding::Rgb,
+                8,
+                &hdmi_2_0,
+                &source()
+            ),
+            Feasibility::Fits(Link::Tmds {
+                char_rate_hz: 594_000_000
+            })
+        );
+        assert_eq!(
+            check(
+                &mode_4k(594_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &hdmi_2_0,
+                &source()
+            ),
+            Feasibility::NeedsYcbcr420(Link::Tmds {
+                char_rate_hz: 371_250_000
+            })
+        );
+        let no_ycbcr420 = SourceCaps {
+            supports_ycbcr420: false,
+            ..source()
+        };
+        assert_eq!(
+            check(
+                &mode_4k(594_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &hdmi_2_0,
+                &no_ycbcr420
+            ),
+            Feasibility::Unsupported
+        );
+
+        assert_eq!(
+            check(
+                &mode_4k(1_188_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &sink(),
+                &source()
+            ),
+            Feasibility::Fits(Link::Frl(HdmiFrl::_10GBps4Lanes))
+        );
+        assert_eq!(
+            check(
+                &mode_4k(1_188_000_000),
+                PixelEncoding::Rgb,
+                12,
+                &sink(),
+                &source()
+            ),
+            Feasibility::Fits(Link::Frl(HdmiFrl::_12GBps4Lanes))
+        );
+        let frl_10g = SinkCaps {
+            supports_dc_36bit_420: true,
+            max_frl_rate: HdmiFrl::_10GBps4Lanes,
+            ..sink()
+        };
+        assert_eq!(
+            check(
+                &mode_4k(1_188_000_000),
+                PixelEncoding::Rgb,
+                12,
+                &frl_10g,
+                &source()
+            ),
+            Feasibility::NeedsYcbcr420(Link::Frl(HdmiFrl::_6GBps4Lanes))
+        );
+    }
+
+    #[test]
+    fn check_dsc() {
+        // The DSC block limits compressed video to a slower rate than the SCDS.
+        let sink = SinkCaps {
+            supports_dc_30bit_420: false,
+            dsc: Some(HdmiDsc {
+                max_frl_rate: HdmiFrl::_8GBps4Lanes,
+                ..hdmi_dsc()
+            }),
+            ..sink()
+        };
+        assert_eq!(
+            check(
+                &mode_8k(2_376_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &sink,
+                &source()
+            ),
+            Feasibility::NeedsDsc(DscConfig {
+                slice_count: 8,
+                bits_per_pixel_x16: 208,
+                frl_rate: HdmiFrl::_8GBps4Lanes,
+            })
+        );
+        // 8K240 exceeds the maximum slice rate.
+        assert_eq!(
+            check(
+                &mode_8k(9_504_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &sink,
+                &source()
+            ),
+            Feasibility::Unsupported
+        );
+
+        let no_dsc = SinkCaps { dsc: None, ..sink };
+        assert_eq!(
+            check(
+                &mode_8k(2_376_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &no_dsc,
+                &source()
+            ),
+            Feasibility::Unsupported
+        );
+    }
+
+    #[test]
+    fn dsc_configs() {
+        let mode = mode_8k(2_376_000_000);
+        let config = |dsc: HdmiDsc| dsc_config(&mode, PixelEncoding::Rgb, 10, &dsc, &source());
+
+        // The fastest rate gives the highest target, in whole bits unless the
+        // sink supports all fractional values.
+        assert_eq!(
+            config(hdmi_dsc()),
+            Ok(DscConfig {
+                slice_count: 8,
+                bits_per_pixel_x16: 304,
+                frl_rate: HdmiFrl::_12GBps4Lanes,
+            })
+        );
+        let all_bpc = HdmiDsc {
+            supports_all_bpc: true,
+            ..hdmi_dsc()
+        };
+        assert_eq!(
+            config(all_bpc).map(|config| config.bits_per_pixel_x16),
+            Ok(319)
+        );
+
+        // 1536 byte chunks of 960 pixel slices limit the target to 12.75 bits,
+        // which the 8 Gbps rate already carries.
+        let chunk_limited = HdmiDsc {
+            max_total_chunk_bytes: 12 * 1024,
+            ..all_bpc
+        };
+        assert_eq!(
+            config(chunk_limited),
+            Ok(DscConfig {
+                slice_count: 8,
+                bits_per_pixel_x16: 204,
+                frl_rate: HdmiFrl::_8GBps4Lanes,
+            })
+        );
+        let chunk_limited = HdmiDsc {
+            supports_all_bpc: false,
+            ..chunk_limited
+        };
+        assert_eq!(
+            config(chunk_limited),
+            Ok(DscConfig {
+                slice_count: 8,
+                bits_per_pixel_x16: 192,
+                frl_rate: HdmiFrl::_8GBps4Lanes,
+            })
+        );
+    }
+
+    #[test]
+    fn dsc_errors() {
+        let mode = mode_8k(2_376_000_000);
+        let unsupported = HdmiDsc {
+            max_slices: HdmiDscMaxSlices::Unsupported,
+            ..hdmi_dsc()
+        };
+        assert_eq!(
+            dsc_config(&mode, PixelEncoding::Rgb, 10, &unsupported, &source()),
+            Err(DscError::Unsupported)
+        );
+        let no_dsc = SourceCaps {
+            supports_dsc: false,
+            ..source()
+        };
+        assert_eq!(
+            dsc_config(&mode, PixelEncoding::Rgb, 10, &hdmi_dsc(), &no_dsc),
+            Err(DscError::Unsupported)
+        );
+        assert_eq!(
+            dsc_config(&mode, PixelEncoding::Ycbcr422, 10, &hdmi_dsc(), &source()),
+            Err(DscError::UnsupportedEncoding(PixelEncoding::Ycbcr422))
+        );
+        assert_eq!(
+            dsc_config(&mode, PixelEncoding::Rgb, 12, &hdmi_dsc(), &source()),
+            Err(DscError::UnsupportedBpc(12))
+        );
+        assert_eq!(
+            dsc_config(
+                &mode_8k(9_504_000_000),
+                PixelEncoding::Rgb,
+                10,
+                &hdmi_dsc(),
+                &source()
+            ),
+            Err(DscError::TooFewSlices)
+        );
+        let slow = HdmiDsc {
+            max_frl_rate: HdmiFrl::_6GBps3Lanes,
+            ..hdmi_dsc()
+        };
+        assert_eq!(
+            dsc_config(&mode, PixelEncoding::Rgb, 10, &slow, &source()),
+            Err(DscError::InsufficientBandwidth)
+        );
+    }
+}
@@ -1,5 +1,12 @@
 //! High-level API.
-use crate::{edid::Edid, ffi, string_from_owned_ffi_ptr};
+use crate::{
+    cvt,
+    displayid::DisplayId,
+    edid::{CvtAspectRatio, DisplayRangeLimitsType, Edid},
+    ffi, gtf,
+    mode::Mode,
+    string_from_owned_ffi_ptr,
+};
 
 #[cfg(any(feature = "v0_2", feature = "v0_3"))]
 use libdisplay_info_derive::FFIFrom;
@@ -65,6 +72,49 @@ pub struct Info(*mut ffi::info::di_info);
 #[error("Parsing the EDID blob failed")]
 pub struct ParseFailed;
 
+/// Generating a mode within the limits of the display failed
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GenerateModeError {
+    /// There is no EDID
+    #[error("no EDID")]
+    NoEdid,
+    /// The EDID has no display range limits
+    #[error("no display range limits")]
+    NoRangeLimits,
+    /// The display doesn't accept timings computed via GTF or CVT
+    #[error("the display doesn't accept generated timings")]
+    Unsupported,
+    /// The aspect ratio isn't supported by the display
+    #[error("unsupported aspect ratio {width}:{height}")]
+    UnsupportedAspectRatio { width: i32, height: i32 },
+    /// The number of active pixels per line exceeds the display's maximum
+    #[error("{width} active pixels per line exceed the maximum of {max}")]
+    TooWide { width: i32, max: i32 },
+    /// The pixel clock exceeds the display's maximum
+    #[error("pixel clock of {pixel_clock_hz} Hz exceeds the maximum of {max} Hz")]
+    PixelClockTooHigh { pixel_clock_hz: i64, max: i64 },
+    /// The horizontal rate is outside of the display's range
+    #[error("horizontal rate of {rate_hz} Hz is outside of {min}-{max} Hz")]
+    HorizRateOutOfRange { rate_hz: f64, min: i32, max: i32 },
+    /// The vertical rate is outside of the display's range
+    #[error("vertical rate of {rate_hz} Hz is outside of {min}-{max} Hz")]
+    VertRateOutOfRange { rate_hz: f64, min: i32, max: i32 },
+}
+
+/// Get the CVT aspect ratio of a resolution, within 1%.
+fn cvt_aspect_ratio(width: i32, height: i32) -> Option<CvtAspectRatio> {
+    [
+        (CvtAspectRatio::_4_3, 4.0 / 3.0),
+        (CvtAspectRatio::_16_9, 16.0 / 9.0),
+        (CvtAspectRatio::_16_10, 16.0 / 10.0),
+        (CvtAspectRatio::_5_4, 5.0 / 4.0),
+        (CvtAspectRatio::_15_9, 15.0 / 9.0),
+    ]
+    .into_iter()
+    .find(|(_, ratio)| (width as f64 / height as f64 - ratio).abs() < ratio * 0.01)
+    .map(|(aspect_ratio, _)| aspect_ratio)
+}
+
 impl Info {
     /// Parse an EDID blob.
     pub fn parse_edid(data: &[u8]) -> Result<Self, ParseFailed> {
@@ -222,6 +272,141 @@ impl Info {
             Some(default_gamma)
         }
     }
+
+    /// Generate a progressive mode the display accepts.
+    ///
+    /// The formula is selected by the display range limits: CVT if they
+    /// carry CVT support information, with reduced blanking when supported,
+    /// otherwise GTF with the curve selected by the range limits. CVT 2.0
+    /// displays get the second version of reduced blanking, later ones the
+    /// third version. The mode is then checked against the maximum pixel
+    /// clock and the horizontal and vertical rate ranges.
+    pub fn generate_mode(
+        &self,
+        width: i32,
+        height: i32,
+        refresh_rate_hz: f64,
+    ) -> Result<Mode, GenerateModeError> {
+        let edid = self.edid().ok_or(GenerateModeError::NoEdid)?;
+        let range_limits = edid
+            .display_descriptors()
+            .iter()
+            .find_map(|descriptor| descriptor.range_limits())
+            .ok_or(GenerateModeError::NoRangeLimits)?;
+        if edid.revision() >= 4 && !edid.misc_features().continuous_freq {
+            return Err(GenerateModeError::Unsupported);
+        }
+
+        let mode: Mode = match (range_limits.type_, range_limits.cvt) {
+            (DisplayRangeLimitsType::Cvt, Some(cvt)) => {
+                if !cvt_aspect_ratio(width, height)
+                    .is_some_and(|aspect_ratio| cvt.supported_aspect_ratio.contains(aspect_ratio))
+                {
+                    return Err(GenerateModeError::UnsupportedAspectRatio { width, height });
+                }
+                if let Some(max) = cvt.max_horiz_px.filter(|max| width > *max) {
+                    return Err(GenerateModeError::TooWide { width, max });
+                }
+
+                let red_blank_ver = if cvt.reduced_blanking {
+                    match (cvt.version, cvt.revision) {
+                        (1, _) => cvt::ReducedBlankingVersion::V1,
+                        (2, 0) => cvt::ReducedBlankingVersion::V2,
+                        _ => cvt::ReducedBlankingVersion::V3,
+                    }
+                } else if cvt.standard_blanking {
+                    cvt::ReducedBlankingVersion::None
+                } else {
+                    return Err(GenerateModeError::Unsupported);
+                };
+                cvt::Timing::compute(cvt::Options::new(
+                    red_blank_ver,
+                    width,
+                    height,
+                    refresh_rate_hz,
+                ))
+                .into()
+            }
+            (DisplayRangeLimitsType::DefaultGtf | DisplayRangeLimitsType::SecondaryGtf, _) => {
+                gtf::Timing::compute_with_range_limits(
+                    width,
+                    height,
+                    refresh_rate_hz,
+                    &range_limits,
+                )
+                .into()
+            }
+            _ => return Err(GenerateModeError::Unsupported),
+        };
+
+        if let Some(max) = range_limits
+            .max_pixel_clock_hz
+            .filter(|max| mode.pixel_clock_hz > *max)
+        {
+            return Err(GenerateModeError::PixelClockTooHigh {
+                pixel_clock_hz: mode.pixel_clock_hz,
+                max,
+            });
+        }
+        let horiz_rate_hz = mode.pixel_clock_hz as f64 / mode.h_total() as f64;
+        if horiz_rate_hz < range_limits.min_horiz_rate_hz as f64
+            || horiz_rate_hz > range_limits.max_horiz_rate_hz as f64
+        {
+            return Err(GenerateModeError::HorizRateOutOfRange {
+                rate_hz: horiz_rate_hz,
+                min: range_limits.min_horiz_rate_hz,
+                max: range_limits.max_horiz_rate_hz,
+            });
+        }
+        let vert_rate_hz = mode.refresh_rate_mhz() as f64 / 1000.0;
+        if vert_rate_hz < range_limits.min_vert_rate_hz as f64
+            || vert_rate_hz > range_limits.max_vert_rate_hz as f64
+        {
+            return Err(GenerateModeError::VertRateOutOfRange {
+                rate_hz: vert_rate_hz,
+                min: range_limits.min_vert_rate_hz,
+                max: range_limits.max_vert_rate_hz,
+            });
+        }
+
+        Ok(mode)
+    }
+
+    /// Generate lower resolution modes for displays which scale internally.
+    ///
+    /// Displays scale when their CVT range limits advertise scaling support,
+    /// or when their DisplayID display parameters report a fixed timing. The
+    /// modes are derived from the preferred detailed timing, see
+    /// [`Mode::scaled_modes`]. Returns an empty list for other displays.
+    pub fn scaled_modes(&self) -> Vec<Mode> {
+        let Some(edid) = self.edid() else {
+            return Vec::new();
+        };
+
+        let cvt_scaling = edid
+            .display_descriptors()
+            .iter()
+            .filter_map(|descriptor| descriptor.range_limits()?.cvt)
+            .any(|cvt| !cvt.supported_scaling.is_empty());
+        let fixed_timing = edid
+            .extensions()
+            .iter()
+            .filter_map(DisplayId::from_extension)
+            .any(|display_id| {
+                display_id
+                    .data_blocks()
+                    .iter()
+                    .filter_map(|data_block| data_block.display_params()?.features)
+                    .any(|features| features.fixed_timing)
+            });
+        if !cvt_scaling && !fixed_timing {
+            return Vec::new();
+        }
+
+        edid.detailed_timing_defs()
+            .next()
+            .map_or_else(Vec::new, |native| Mode::from(native).scaled_modes())
+    }
 }
 
 impl Drop for Info {
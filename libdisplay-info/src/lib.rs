@@ -13,15 +13,25 @@ mod feature_check {
 
 pub use libdisplay_info_sys as ffi;
 
+pub mod cmdline;
 pub mod cta;
 pub mod cvt;
 pub mod displayid;
 #[cfg(feature = "v0_3")]
 pub mod displayid2;
 pub mod dmt;
+pub mod dp;
+pub mod drm;
 pub mod edid;
 pub mod gtf;
+#[cfg(feature = "v0_3")]
+pub mod hdmi;
 pub mod info;
+pub mod mode;
+pub mod modeline;
+pub mod patch;
+pub mod text;
+pub mod validate;
 
 /// Get the [`String`] from an owned ffi ptr
 ///
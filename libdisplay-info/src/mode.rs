@@ -0,0 +1,856 @@
+//! Common representation of video timings.
+//!
+//! The timing structures of the other modules mirror their specifications, and
+//! differ in units, rounding and in how they describe borders and interlaced
+//! formats. [`Mode`] provides a single integer representation, similar to the
+//! modes used by DRM and X11, which all of them can be converted into.
+#[cfg(feature = "v0_3")]
+use crate::cta::{HdmiScds, HdmiVideoFormat};
+use crate::{
+    cta::{VideoFormat, VideoFormatSyncPolarity},
+    cvt,
+    displayid::{TypeIIIVIITiming, TypeIIIVIITimingSyncPolarity},
+    dmt,
+    edid::{DetailedTimingDef, DetailedTimingDefSyncPolarity, DisplayRangeLimits},
+    gtf,
+};
+
+/// Polarity of a sync pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyncPolarity {
+    Negative,
+    Positive,
+}
+
+/// Pixel encoding of a video signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelEncoding {
+    Rgb,
+    Ycbcr444,
+    Ycbcr422,
+    Ycbcr420,
+}
+
+impl PixelEncoding {
+    /// Get the number of bits per pixel at a bit depth.
+    pub fn bits_per_pixel(&self, bpc: u8) -> f64 {
+        let bpc = bpc as f64;
+        match self {
+            PixelEncoding::Rgb | PixelEncoding::Ycbcr444 => 3.0 * bpc,
+            PixelEncoding::Ycbcr422 => 2.0 * bpc,
+            PixelEncoding::Ycbcr420 => 1.5 * bpc,
+        }
+    }
+}
+
+/// A video timing.
+///
+/// Borders are included in the front and back porches. For interlaced modes
+/// the vertical values describe a whole frame: the active lines of both fields
+/// are added up, field porches and sync pulses are doubled, and the total line
+/// count includes the extra half line of each field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mode {
+    pub pixel_clock_hz: i64,
+    pub h_active: i32,
+    pub h_front: i32,
+    pub h_sync: i32,
+    pub h_back: i32,
+    pub v_active: i32,
+    pub v_front: i32,
+    pub v_sync: i32,
+    pub v_back: i32,
+    pub h_sync_polarity: SyncPolarity,
+    pub v_sync_polarity: SyncPolarity,
+    pub interlaced: bool,
+}
+
+impl Mode {
+    /// Total number of pixels per line, including blanking.
+    pub fn h_total(&self) -> i32 {
+        self.h_active + self.h_front + self.h_sync + self.h_back
+    }
+
+    /// Total number of lines per frame, including blanking.
+    pub fn v_total(&self) -> i32 {
+        self.v_active + self.v_front + self.v_sync + self.v_back
+    }
+
+    /// Get the refresh rate in Hz, as a reduced fraction.
+    ///
+    /// The first element is the numerator, the second one the denominator.
+    /// Interlaced modes report their field rate.
+    pub fn refresh_rate(&self) -> (u64, u64) {
+        let fields = if self.interlaced { 2 } else { 1 };
+        let numerator = self.pixel_clock_hz.max(0) as u64 * fields;
+        let denominator = self.h_total().max(0) as u64 * self.v_total().max(0) as u64;
+        if denominator == 0 {
+            return (0, 1);
+        }
+
+        let divisor = gcd(numerator, denominator);
+        (numerator / divisor, denominator / divisor)
+    }
+
+    /// Get the refresh rate in mHz, rounded to the nearest integer.
+    pub fn refresh_rate_mhz(&self) -> u64 {
+        let (numerator, denominator) = self.refresh_rate();
+        (numerator * 1000 + denominator / 2) / denominator
+    }
+
+    /// Build a mode from field based vertical values, as stored in EDID
+    /// detailed timing definitions.
+    pub(crate) fn from_fields(
+        v_active: i32,
+        v_front: i32,
+        v_sync: i32,
+        v_blank: i32,
+        interlaced: bool,
+        horizontal: Mode,
+    ) -> Self {
+        let v_back = v_blank - v_front - v_sync;
+        if interlaced {
+            Mode {
+                v_active: 2 * v_active,
+                v_front: 2 * v_front,
+                v_sync: 2 * v_sync,
+                v_back: 2 * v_back + 1,
+                interlaced,
+                ..horizontal
+            }
+        } else {
+            Mode {
+                v_active,
+                v_front,
+                v_sync,
+                v_back,
+                interlaced,
+                ..horizontal
+            }
+        }
+    }
+}
+
+/// Standard or formula a timing is defined by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// CTA-861 video format, with its VIC
+    Vic(u8),
+    /// HDMI 1.4b video format, with its HDMI VIC
+    #[cfg(feature = "v0_3")]
+    HdmiVic(u8),
+    /// VESA DMT timing, with its DMT ID
+    Dmt(u8),
+    /// VESA CVT timing, with its reduced blanking version
+    Cvt(cvt::ReducedBlankingVersion),
+    /// VESA GTF timing, computed with the default parameters
+    Gtf,
+    /// None of the above
+    Custom,
+}
+
+/// Maximum pixel clock difference of matching timings. EDID detailed timing
+/// definitions store the pixel clock in units of 10 kHz, while the formulas
+/// and tables have a finer precision.
+const CLOCK_TOLERANCE_HZ: i64 = 10_000;
+
+impl Mode {
+    /// Identify the standard or formula the mode is defined by.
+    ///
+    /// Returns the first entry of [`Mode::origins`], or [`Origin::Custom`] if
+    /// it is empty.
+    pub fn origin(&self) -> Origin {
+        self.origins().first().copied().unwrap_or(Origin::Custom)
+    }
+
+    /// Find all standards and formulas the mode is defined by.
+    ///
+    /// Timings are compared on their active, porch and sync sizes and on
+    /// their pixel clock, up to the 10 kHz precision of EDID detailed timing
+    /// definitions. Sync polarities are ignored. CTA-861 video formats also
+    /// match at their 1000/1001 fractional rates.
+    ///
+    /// Matches are ordered by VIC, HDMI VIC, DMT ID, CVT and GTF. A format
+    /// shared by several VICs, like the 4:3 and 16:9 variants of the same
+    /// timing, yields one entry per VIC. CVT reduced blanking version 3 is
+    /// only matched without additional horizontal blanking.
+    pub fn origins(&self) -> Vec<Origin> {
+        let mut origins = Vec::new();
+
+        origins.extend(
+            VideoFormat::find(self, None)
+                .into_iter()
+                .map(|format| Origin::Vic(format.vic)),
+        );
+
+        #[cfg(feature = "v0_3")]
+        origins.extend(HdmiVideoFormat::find(self).map(|format| Origin::HdmiVic(format.vic)));
+
+        origins.extend(
+            dmt::Timing::all()
+                .filter(|timing| self.matches(&Mode::from(*timing)))
+                .map(|timing| Origin::Dmt(timing.dmt_id)),
+        );
+
+        let refresh_hz = ((self.refresh_rate_mhz() + 500) / 1000) as f64;
+        if self.interlaced || refresh_hz == 0.0 {
+            return origins;
+        }
+
+        for red_blank_ver in [
+            cvt::ReducedBlankingVersion::None,
+            cvt::ReducedBlankingVersion::V1,
+            cvt::ReducedBlankingVersion::V2,
+            cvt::ReducedBlankingVersion::V3,
+        ] {
+            // Only the second version has a variant for fractional rates.
+            let video_opts: &[bool] = match red_blank_ver {
+                cvt::ReducedBlankingVersion::V2 => &[false, true],
+                _ => &[false],
+            };
+            let found = video_opts.iter().any(|video_opt| {
+                let timing = cvt::Timing::compute(cvt::Options {
+                    video_opt: *video_opt,
+                    ..cvt::Options::new(red_blank_ver, self.h_active, self.v_active, refresh_hz)
+                });
+                self.matches(&timing.into())
+            });
+            if found {
+                origins.push(Origin::Cvt(red_blank_ver));
+            }
+        }
+
+        let timing = gtf::Timing::compute(gtf::Options {
+            h_pixels: self.h_active,
+            v_lines: self.v_active,
+            margins_rqd: false,
+            ip_param: gtf::IpParam::VFrameRate,
+            ip_freq_rqd: refresh_hz,
+            int_rqd: false,
+            m: gtf::DEFAULT_M,
+            c: gtf::DEFAULT_C,
+            k: gtf::DEFAULT_K,
+            j: gtf::DEFAULT_J,
+        });
+        if self.matches(&timing.into()) {
+            origins.push(Origin::Gtf);
+        }
+
+        origins
+    }
+
+    /// Check whether two modes describe the same timing, regardless of their
+    /// sync polarities.
+    pub(crate) fn matches(&self, other: &Mode) -> bool {
+        self.h_active == other.h_active
+            && self.h_front == other.h_front
+            && self.h_sync == other.h_sync
+            && self.h_back == other.h_back
+            && self.v_active == other.v_active
+            && self.v_front == other.v_front
+            && self.v_sync == other.v_sync
+            && self.v_back == other.v_back
+            && self.interlaced == other.interlaced
+            && (self.pixel_clock_hz - other.pixel_clock_hz).abs() <= CLOCK_TOLERANCE_HZ
+    }
+}
+
+/// Refresh rate range of a variable refresh rate sink, in Hz.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VrrRange {
+    pub min_hz: i32,
+    pub max_hz: i32,
+}
+
+impl From<&DisplayRangeLimits> for VrrRange {
+    fn from(range_limits: &DisplayRangeLimits) -> Self {
+        VrrRange {
+            min_hz: range_limits.min_vert_rate_hz,
+            max_hz: range_limits.max_vert_rate_hz,
+        }
+    }
+}
+
+impl VrrRange {
+    /// Get the VRR range of an HDMI sink from its Sink Capability Data
+    /// Structure.
+    ///
+    /// Returns `None` if the sink doesn't support VRR, i.e. if it has no
+    /// minimum rate. Without a maximum rate, the sink supports up to the rate
+    /// of each mode.
+    #[cfg(feature = "v0_3")]
+    pub fn from_scds(scds: &HdmiScds) -> Option<Self> {
+        (scds.vrr_min_hz > 0).then_some(VrrRange {
+            min_hz: scds.vrr_min_hz,
+            max_hz: if scds.vrr_max_hz > 0 {
+                scds.vrr_max_hz
+            } else {
+                i32::MAX
+            },
+        })
+    }
+}
+
+/// Refresh rates of the fixed rate VRR modes in mHz, including the 1000/1001
+/// fractional video rates.
+const VRR_RATES_MHZ: [u64; 17] = [
+    23_976, 24_000, 25_000, 29_970, 30_000, 48_000, 50_000, 59_940, 60_000, 72_000, 90_000, 96_000,
+    100_000, 119_880, 120_000, 144_000, 165_000,
+];
+
+impl Mode {
+    /// Derive fixed rate modes from a mode, for a sink with variable refresh
+    /// rate support.
+    ///
+    /// The modes keep the pixel clock and the horizontal timing, and stretch
+    /// the vertical front porch to lower the refresh rate to common video and
+    /// gaming rates within the VRR range. Switching between them doesn't
+    /// require a full modeset on sinks which are in VRR mode. Rates above the
+    /// one of the mode can't be reached, interlaced modes yield no mode.
+    pub fn vrr_modes(&self, range: VrrRange) -> Vec<Mode> {
+        let base_rate_mhz = self.refresh_rate_mhz();
+        if self.interlaced || self.h_total() <= 0 {
+            return Vec::new();
+        }
+
+        VRR_RATES_MHZ
+            .iter()
+            .filter(|rate_mhz| {
+                **rate_mhz < base_rate_mhz
+                    && **rate_mhz >= range.min_hz.max(0) as u64 * 1000
+                    && **rate_mhz <= range.max_hz.max(0) as u64 * 1000
+            })
+            .filter_map(|rate_mhz| {
+                let v_total = (self.pixel_clock_hz as u64 * 1000 * 2
+                    / (self.h_total() as u64 * rate_mhz)
+                    + 1)
+                    / 2;
+                let stretch = i32::try_from(v_total).ok()? - self.v_total();
+                (stretch > 0).then_some(Mode {
+                    v_front: self.v_front + stretch,
+                    ..*self
+                })
+            })
+            .collect()
+    }
+}
+
+/// Common 4:3, 5:4, 16:9 and 16:10 resolutions offered below the native one
+/// of displays which scale internally.
+const COMMON_RESOLUTIONS: [(i32, i32); 16] = [
+    (640, 480),
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1280, 800),
+    (1280, 1024),
+    (1366, 768),
+    (1440, 900),
+    (1600, 900),
+    (1600, 1200),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+    (2560, 1440),
+    (2560, 1600),
+    (3840, 2160),
+];
+
+impl Mode {
+    /// Generate the common resolutions fitting within the mode, for displays
+    /// scaling other resolutions to their native one.
+    ///
+    /// The modes are computed via CVT with reduced blanking, at the refresh
+    /// rate of the mode, from the smallest resolution. CVT rounds the width
+    /// down to a multiple of 8 pixels, the missing pixels of widths such as
+    /// 1366 are taken from the horizontal front porch.
+    pub fn scaled_modes(&self) -> Vec<Mode> {
+        self.scaled_modes_with(cvt::Timing::compute)
+    }
+
+    /// Generate the common resolutions fitting within the mode, computing the
+    /// CVT timings with `compute`.
+    fn scaled_modes_with(&self, compute: impl Fn(cvt::Options) -> cvt::Timing) -> Vec<Mode> {
+        let refresh_hz = self.refresh_rate_mhz() as f64 / 1000.0;
+        COMMON_RESOLUTIONS
+            .iter()
+            .filter(|(width, height)| {
+                *width <= self.h_active
+                    && *height <= self.v_active
+                    && (*width, *height) != (self.h_active, self.v_active)
+            })
+            .map(|(width, height)| {
+                let mut mode: Mode = compute(cvt::Options::new(
+                    cvt::ReducedBlankingVersion::V1,
+                    *width,
+                    *height,
+                    refresh_hz,
+                ))
+                .into();
+                mode.h_front -= width - mode.h_active;
+                mode.h_active = *width;
+                mode
+            })
+            .collect()
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+/// Type of the connector a display is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectorType {
+    Vga,
+    Dvi,
+    Hdmi,
+    DisplayPort,
+}
+
+/// DMT IDs of the fallback modes, 640x480@60, 800x600@60 and 1024x768@60.
+/// All of them fit within the single link DVI and the HDMI 1.0 TMDS character
+/// rate.
+const SAFE_DMT_IDS: [u8; 3] = [0x04, 0x09, 0x10];
+
+/// DMT ID of 640x480@60, the DisplayPort fail-safe mode.
+const DP_FAIL_SAFE_DMT_ID: u8 = 0x04;
+
+/// VIC of 640x480@60, which all HDMI sinks must support.
+const HDMI_MANDATORY_VIC: u8 = 1;
+
+impl Mode {
+    /// Get a conservative list of modes for a display connected to the given
+    /// connector type.
+    ///
+    /// This is meant for displays without an EDID, or whose EDID fails to
+    /// parse or doesn't advertise any usable mode. The modes are ordered from
+    /// the safest one:
+    ///
+    /// - VGA and DVI get the DMT 640x480, 800x600 and 1024x768 modes at 60 Hz.
+    /// - HDMI gets the same modes, with CTA-861 VIC 1 instead of the
+    ///   equivalent DMT 640x480 timing, as all HDMI sinks must support it.
+    /// - DisplayPort only gets the 640x480@60 fail-safe mode, the only one
+    ///   DP 1.4 section 5.2.1.2 guarantees when the EDID can't be used.
+    pub fn safe_defaults(connector: ConnectorType) -> Vec<Mode> {
+        let dmt_ids: &[u8] = match connector {
+            ConnectorType::Vga | ConnectorType::Dvi | ConnectorType::Hdmi => &SAFE_DMT_IDS,
+            ConnectorType::DisplayPort => &[DP_FAIL_SAFE_DMT_ID],
+        };
+
+        let mut modes = Vec::new();
+        if connector == ConnectorType::Hdmi {
+            modes.extend(VideoFormat::from_vic(HDMI_MANDATORY_VIC).map(Mode::from));
+        }
+        for timing in dmt_ids.iter().filter_map(|id| dmt::Timing::from_id(*id)) {
+            let mode = Mode::from(timing);
+            if !modes.iter().any(|other| mode.matches(other)) {
+                modes.push(mode);
+            }
+        }
+        modes
+    }
+}
+
+impl From<DetailedTimingDefSyncPolarity> for SyncPolarity {
+    fn from(polarity: DetailedTimingDefSyncPolarity) -> Self {
+        match polarity {
+            DetailedTimingDefSyncPolarity::Negative => SyncPolarity::Negative,
+            DetailedTimingDefSyncPolarity::Positive => SyncPolarity::Positive,
+        }
+    }
+}
+
+impl From<VideoFormatSyncPolarity> for SyncPolarity {
+    fn from(polarity: VideoFormatSyncPolarity) -> Self {
+        match polarity {
+            VideoFormatSyncPolarity::Negative => SyncPolarity::Negative,
+            VideoFormatSyncPolarity::Positive => SyncPolarity::Positive,
+        }
+    }
+}
+
+impl From<TypeIIIVIITimingSyncPolarity> for SyncPolarity {
+    fn from(polarity: TypeIIIVIITimingSyncPolarity) -> Self {
+        match polarity {
+            TypeIIIVIITimingSyncPolarity::Negative => SyncPolarity::Negative,
+            TypeIIIVIITimingSyncPolarity::Positive => SyncPolarity::Positive,
+        }
+    }
+}
+
+/// Sync polarities of DMT timings, which aren't part of [`dmt::Timing`].
+fn dmt_sync_polarities(timing: &dmt::Timing) -> (SyncPolarity, SyncPolarity) {
+    use SyncPolarity::*;
+
+    match timing.dmt_id {
+        0x53 | 0x54 | 0x56 => (Positive, Positive),
+        _ if timing.reduced_blanking => (Positive, Negative),
+        0x01 => (Positive, Negative),
+        0x04..=0x07 | 0x10 | 0x11 => (Negative, Negative),
+        0x02
+        | 0x03
+        | 0x17..=0x19
+        | 0x1c..=0x1e
+        | 0x2a..=0x2c
+        | 0x2f..=0x31
+        | 0x3a..=0x3c
+        | 0x3e
+        | 0x3f
+        | 0x41
+        | 0x42
+        | 0x45..=0x47
+        | 0x49
+        | 0x4a
+        | 0x4d..=0x4f => (Negative, Positive),
+        _ => (Positive, Positive),
+    }
+}
+
+/// DMT ID of the only interlaced DMT timing, 1024x768i at 43 Hz.
+const DMT_ID_INTERLACED: u8 = 0x0f;
+
+impl From<dmt::Timing> for Mode {
+    fn from(timing: dmt::Timing) -> Self {
+        let (h_sync_polarity, v_sync_polarity) = dmt_sync_polarities(&timing);
+        let interlaced = timing.dmt_id == DMT_ID_INTERLACED;
+        let horizontal = Mode {
+            pixel_clock_hz: timing.pixel_clock_hz as i64,
+            h_active: timing.horiz_video,
+            h_front: timing.horiz_front_porch + timing.horiz_border,
+            h_sync: timing.horiz_sync_pulse,
+            h_back: timing.horiz_blank - timing.horiz_front_porch - timing.horiz_sync_pulse
+                + timing.horiz_border,
+            v_active: 0,
+            v_front: 0,
+            v_sync: 0,
+            v_back: 0,
+            h_sync_polarity,
+            v_sync_polarity,
+            interlaced,
+        };
+
+        // The vertical values of the interlaced timing are given per field,
+        // except for the active line count.
+        let v_active = if interlaced {
+            timing.vert_video / 2
+        } else {
+            timing.vert_video
+        };
+        let mut mode = Mode::from_fields(
+            v_active,
+            timing.vert_front_porch,
+            timing.vert_sync_pulse,
+            timing.vert_blank,
+            interlaced,
+            horizontal,
+        );
+        mode.v_front += timing.vert_border;
+        mode.v_back += timing.vert_border;
+        mode
+    }
+}
+
+impl From<cvt::Timing> for Mode {
+    /// CVT timings don't carry their input options: the reduced blanking
+    /// variants are recognized by their fixed 32 pixel horizontal sync pulse,
+    /// and the timing is assumed to be progressive.
+    fn from(timing: cvt::Timing) -> Self {
+        let h_sync = timing.h_sync.round() as i32;
+        let (h_sync_polarity, v_sync_polarity) = if h_sync == 32 {
+            (SyncPolarity::Positive, SyncPolarity::Negative)
+        } else {
+            (SyncPolarity::Negative, SyncPolarity::Positive)
+        };
+
+        Mode {
+            pixel_clock_hz: (timing.act_pixel_freq * 1_000_000.0).round() as i64,
+            h_active: timing.total_active_pixels.round() as i32,
+            h_front: timing.h_front_porch.round() as i32,
+            h_sync,
+            h_back: timing.h_back_porch.round() as i32,
+            v_active: timing.v_lines_rnd.round() as i32,
+            v_front: timing.v_front_porch.round() as i32,
+            v_sync: timing.v_sync.round() as i32,
+            v_back: timing.v_back_porch.round() as i32,
+            h_sync_polarity,
+            v_sync_polarity,
+            interlaced: false,
+        }
+    }
+}
+
+impl From<gtf::Timing> for Mode {
+    /// GTF timings don't carry their input options, the timing is assumed to
+    /// be progressive.
+    fn from(timing: gtf::Timing) -> Self {
+        Mode {
+            pixel_clock_hz: (timing.pixel_freq_mhz * 1_000_000.0).round() as i64,
+            h_active: timing.h_pixels,
+            h_front: timing.h_front_porch + timing.h_border,
+            h_sync: timing.h_sync,
+            h_back: timing.h_back_porch + timing.h_border,
+            v_active: timing.v_lines,
+            v_front: timing.v_front_porch + timing.v_border,
+            v_sync: timing.v_sync,
+            v_back: timing.v_back_porch + timing.v_border,
+            h_sync_polarity: SyncPolarity::Negative,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+}
+
+impl From<DetailedTimingDef> for Mode {
+    /// Only digital sync signals carry sync polarities, the vertical sync
+    /// polarity of digital composite signals and both polarities of analog
+    /// signals are reported as negative.
+    fn from(timing: DetailedTimingDef) -> Self {
+        let (h_sync_polarity, v_sync_polarity) =
+            match (timing.digital_separate, timing.digital_composite) {
+                (Some(separate), _) => (
+                    separate.sync_horiz_polarity.into(),
+                    separate.sync_vert_polarity.into(),
+                ),
+                (None, Some(composite)) => {
+                    (composite.sync_horiz_polarity.into(), SyncPolarity::Negative)
+                }
+                (None, None) => (SyncPolarity::Negative, SyncPolarity::Negative),
+            };
+        let horiz_border = timing.horiz_border.unwrap_or(0);
+        let vert_border = timing.vert_border.unwrap_or(0);
+
+        let mut mode = Mode::from_fields(
+            timing.vert_video,
+            timing.vert_front_porch,
+            timing.vert_sync_pulse,
+            timing.vert_blank,
+            timing.interlaced,
+            Mode {
+                pixel_clock_hz: timing.pixel_clock_hz as i64,
+                h_active: timing.horiz_video,
+                h_front: timing.horiz_front_porch + horiz_border,
+                h_sync: timing.horiz_sync_pulse,
+                h_back: timing.horiz_blank - timing.horiz_front_porch - timing.horiz_sync_pulse
+                    + horiz_border,
+                v_active: 0,
+                v_front: 0,
+                v_sync: 0,
+                v_back: 0,
+                h_sync_polarity,
+                v_sync_polarity,
+                interlaced: timing.interlaced,
+            },
+        );
+        mode.v_front += vert_border;
+        mode.v_back += vert_border;
+        mode
+    }
+}
+
+impl From<VideoFormat> for Mode {
+    /// The active line count of interlaced CTA-861 video formats is already
+    /// given per frame, while the blanking is given per field.
+    fn from(format: VideoFormat) -> Self {
+        let horizontal = Mode {
+            pixel_clock_hz: format.pixel_clock_hz,
+            h_active: format.h_active,
+            h_front: format.h_front,
+            h_sync: format.h_sync,
+            h_back: format.h_back,
+            v_active: 0,
+            v_front: 0,
+            v_sync: 0,
+            v_back: 0,
+            h_sync_polarity: format.h_sync_polarity.into(),
+            v_sync_polarity: format.v_sync_polarity.into(),
+            interlaced: format.interlaced,
+        };
+
+        if format.interlaced {
+            Mode::from_fields(
+                format.v_active / 2,
+                format.v_front,
+                format.v_sync,
+                format.v_front + format.v_sync + format.v_back,
+                true,
+                horizontal,
+            )
+        } else {
+            Mode {
+                v_active: format.v_active,
+                v_front: format.v_front,
+                v_sync: format.v_sync,
+                v_back: format.v_back,
+                ..horizontal
+            }
+        }
+    }
+}
+
+impl From<TypeIIIVIITiming> for Mode {
+    /// Like EDID detailed timing definitions, the vertical values of
+    /// interlaced timings are given per field.
+    fn from(timing: TypeIIIVIITiming) -> Self {
+        Mode::from_fields(
+            timing.vert_active,
+            timing.vert_offset,
+            timing.vert_sync_width,
+            timing.vert_blank,
+            timing.interlaced,
+            Mode {
+                pixel_clock_hz: (timing.pixel_clock_mhz * 1_000_000.0).round() as i64,
+                h_active: timing.horiz_active,
+                h_front: timing.horiz_offset,
+                h_sync: timing.horiz_sync_width,
+                h_back: timing.horiz_blank - timing.horiz_offset - timing.horiz_sync_width,
+                v_active: 0,
+                v_front: 0,
+                v_sync: 0,
+                v_back: 0,
+                h_sync_polarity: timing.horiz_sync_polarity.into(),
+                v_sync_polarity: timing.vert_sync_polarity.into(),
+                interlaced: timing.interlaced,
+            },
+        )
+    }
+}
+
+#[cfg(feature = "v0_3")]
+impl From<HdmiVideoFormat> for Mode {
+    /// All HDMI video formats are progressive, with positive sync polarities.
+    fn from(format: HdmiVideoFormat) -> Self {
+        Mode {
+            pixel_clock_hz: format.pixel_clock_hz,
+            h_active: format.h_active,
+            h_front: format.h_front,
+            h_sync: format.h_sync,
+            h_back: format.h_back,
+            v_active: format.v_active,
+            v_front: format.v_front,
+            v_sync: format.v_sync,
+            v_back: format.v_back,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The CTA-861 timings of VIC 16, 1920x1080 at 60 Hz.
+    fn mode_1080p60() -> Mode {
+        Mode {
+            pixel_clock_hz: 148_500_000,
+            h_active: 1920,
+            h_front: 88,
+            h_sync: 44,
+            h_back: 148,
+            v_active: 1080,
+            v_front: 4,
+            v_sync: 5,
+            v_back: 36,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    #[test]
+    fn vrr_modes() {
+        let mode = mode_1080p60();
+        let modes = mode.vrr_modes(VrrRange {
+            min_hz: 40,
+            max_hz: 144,
+        });
+        assert!(modes.iter().all(|vrr_mode| Mode {
+            v_front: mode.v_front,
+            ..*vrr_mode
+        } == mode));
+        let rates_mhz: Vec<_> = modes.iter().map(Mode::refresh_rate_mhz).collect();
+        assert_eq!(rates_mhz, [48_009, 50_000, 59_947]);
+        let v_fronts: Vec<_> = modes.iter().map(|mode| mode.v_front).collect();
+        assert_eq!(v_fronts, [285, 229, 5]);
+
+        let modes = mode.vrr_modes(VrrRange {
+            min_hz: 24,
+            max_hz: 50,
+        });
+        let rates_mhz: Vec<_> = modes.iter().map(Mode::refresh_rate_mhz).collect();
+        assert_eq!(rates_mhz, [23_996, 25_000, 29_973, 30_000, 48_009, 50_000]);
+
+        let interlaced = Mode {
+            interlaced: true,
+            ..mode
+        };
+        assert_eq!(
+            interlaced.vrr_modes(VrrRange {
+                min_hz: 24,
+                max_hz: 60
+            }),
+            []
+        );
+    }
+
+    #[test]
+    fn scaled_modes() {
+        // CVT with reduced blanking version 1, with the width rounded down to
+        // a multiple of 8 pixels.
+        let compute = |options: cvt::Options| {
+            assert_eq!(options.red_blank_ver, cvt::ReducedBlankingVersion::V1);
+            assert_eq!(options.ip_freq_rqd, 60.0);
+            cvt::Timing {
+                act_pixel_freq: 100.0,
+                total_active_pixels: (options.h_pixels / 8 * 8) as f64,
+                v_lines_rnd: options.v_lines as f64,
+                h_front_porch: 48.0,
+                h_sync: 32.0,
+                h_back_porch: 80.0,
+                v_front_porch: 3.0,
+                v_sync: 6.0,
+                v_back_porch: 20.0,
+                act_frame_rate: 60.0,
+            }
+        };
+        let modes = mode_1080p60().scaled_modes_with(compute);
+
+        let sizes: Vec<_> = modes
+            .iter()
+            .map(|mode| (mode.h_active, mode.v_active))
+            .collect();
+        // 1600x1200 is too tall, and 1920x1080 is the native resolution.
+        assert_eq!(
+            sizes,
+            [&COMMON_RESOLUTIONS[..9], &COMMON_RESOLUTIONS[10..11]].concat()
+        );
+
+        let mode = modes[6];
+        assert_eq!(
+            (mode.h_active, mode.h_front, mode.h_total()),
+            (1366, 42, 1520)
+        );
+        assert_eq!((modes[5].h_front, modes[5].h_total()), (48, 1440));
+    }
+
+    #[test]
+    fn common_resolutions() {
+        assert!(COMMON_RESOLUTIONS.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let aspect_ratios = [(4, 3), (5, 4), (16, 9), (16, 10)];
+        for (width, height) in COMMON_RESOLUTIONS {
+            // 1366x768 is only approximately 16:9.
+            assert!(
+                aspect_ratios
+                    .iter()
+                    .any(|(h, v)| (width * v - height * h).abs() <= height * h / 200),
+                "{width}x{height}"
+            );
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! X11 modelines.
+//!
+//! Modelines are the textual timing format of `xorg.conf`, `xrandr --newmode`
+//! and tools like `cvt` and `gtf`:
+//!
+//! ```text
+//! Modeline "1920x1080" 148.50 1920 2008 2052 2200 1080 1084 1089 1125 +hsync +vsync
+//! ```
+//!
+//! Any timing of the crate can be turned into a modeline by converting it to
+//! a [`Mode`] first.
+use std::{fmt, str::FromStr};
+
+use crate::mode::{Mode, SyncPolarity};
+
+/// An X11 modeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Modeline {
+    pub name: String,
+    pub mode: Mode,
+    /// Whether each line is scanned twice
+    pub double_scan: bool,
+}
+
+impl Modeline {
+    /// Create a modeline, named after the resolution of the mode.
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            name: format!(
+                "{}x{}{}",
+                mode.h_active,
+                mode.v_active,
+                if mode.interlaced { "i" } else { "" }
+            ),
+            mode,
+            double_scan: false,
+        }
+    }
+}
+
+impl From<Mode> for Modeline {
+    fn from(mode: Mode) -> Self {
+        Modeline::new(mode)
+    }
+}
+
+impl From<Modeline> for Mode {
+    fn from(modeline: Modeline) -> Self {
+        modeline.mode
+    }
+}
+
+/// Parsing a modeline failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseModelineError {
+    /// The name is missing or its closing quote is missing
+    #[error("missing or unterminated mode name")]
+    InvalidName,
+    /// A timing value is missing
+    #[error("missing {0}")]
+    MissingField(&'static str),
+    /// A timing value isn't a valid number
+    #[error("invalid {field}: {value:?}")]
+    InvalidNumber { field: &'static str, value: String },
+    /// A flag isn't supported
+    #[error("unknown flag {0:?}")]
+    UnknownFlag(String),
+    /// The sync pulses aren't ordered within the blanking
+    #[error("timing values are out of order")]
+    InvalidTiming,
+}
+
+/// Field names of the timing values, in modeline order.
+const FIELDS: [&str; 8] = [
+    "hdisp",
+    "hsyncstart",
+    "hsyncend",
+    "htotal",
+    "vdisp",
+    "vsyncstart",
+    "vsyncend",
+    "vtotal",
+];
+
+impl FromStr for Modeline {
+    type Err = ParseModelineError;
+
+    /// Parse a modeline, with or without the leading `Modeline` keyword.
+    ///
+    /// Sync polarities which aren't specified default to negative. The
+    /// composite sync, `HSkew`, `VScan` and `BCast` flags are accepted but
+    /// can't be represented by a [`Mode`], and are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start();
+        let s = match s.get(..8).zip(s.get(8..)) {
+            Some((keyword, rest))
+                if keyword.eq_ignore_ascii_case("modeline")
+                    && rest.starts_with(|c: char| c.is_whitespace() || c == '"') =>
+            {
+                rest.trim_start()
+            }
+            _ => s,
+        };
+
+        let (name, rest) = if let Some(quoted) = s.strip_prefix('"') {
+            quoted
+                .split_once('"')
+                .ok_or(ParseModelineError::InvalidName)?
+        } else {
+            s.split_once(char::is_whitespace)
+                .ok_or(ParseModelineError::InvalidName)?
+        };
+        if name.is_empty() {
+            return Err(ParseModelineError::InvalidName);
+        }
+
+        let mut words = rest.split_whitespace();
+        let clock = words
+            .next()
+            .ok_or(ParseModelineError::MissingField("clock"))?;
+        let pixel_clock_hz =
+            parse_clock_mhz(clock).ok_or_else(|| ParseModelineError::InvalidNumber {
+                field: "clock",
+                value: clock.to_string(),
+            })?;
+
+        let mut values = [0i32; 8];
+        for (value, field) in values.iter_mut().zip(FIELDS) {
+            let word = words
+                .next()
+                .ok_or(ParseModelineError::MissingField(field))?;
+            *value = word
+                .parse()
+                .ok()
+                .filter(|value| *value >= 0)
+                .ok_or_else(|| ParseModelineError::InvalidNumber {
+                    field,
+                    value: word.to_string(),
+                })?;
+        }
+        let ordered = |values: &[i32]| values.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !ordered(&values[..4]) || !ordered(&values[4..]) {
+            return Err(ParseModelineError::InvalidTiming);
+        }
+        let [hdisp, hsyncstart, hsyncend, htotal, vdisp, vsyncstart, vsyncend, vtotal] = values;
+
+        let mut h_sync_polarity = SyncPolarity::Negative;
+        let mut v_sync_polarity = SyncPolarity::Negative;
+        let mut interlaced = false;
+        let mut double_scan = false;
+        while let Some(flag) = words.next() {
+            match flag.to_ascii_lowercase().as_str() {
+                "+hsync" => h_sync_polarity = SyncPolarity::Positive,
+                "-hsync" => h_sync_polarity = SyncPolarity::Negative,
+                "+vsync" => v_sync_polarity = SyncPolarity::Positive,
+                "-vsync" => v_sync_polarity = SyncPolarity::Negative,
+                "interlace" => interlaced = true,
+                "doublescan" => double_scan = true,
+                "composite" | "+csync" | "-csync" | "bcast" => {}
+                "hskew" => skip_flag_value(&mut words, "hskew")?,
+                "vscan" => skip_flag_value(&mut words, "vscan")?,
+                _ => return Err(ParseModelineError::UnknownFlag(flag.to_string())),
+            }
+        }
+
+        Ok(Modeline {
+            name: name.to_string(),
+            mode: Mode {
+                pixel_clock_hz,
+                h_active: hdisp,
+                h_front: hsyncstart - hdisp,
+                h_sync: hsyncend - hsyncstart,
+                h_back: htotal - hsyncend,
+                v_active: vdisp,
+                v_front: vsyncstart - vdisp,
+                v_sync: vsyncend - vsyncstart,
+                v_back: vtotal - vsyncend,
+                h_sync_polarity,
+                v_sync_polarity,
+                interlaced,
+            },
+            double_scan,
+        })
+    }
+}
+
+/// Check and skip the value of a flag which isn't represented in [`Mode`].
+fn skip_flag_value<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+    field: &'static str,
+) -> Result<(), ParseModelineError> {
+    let word = words
+        .next()
+        .ok_or(ParseModelineError::MissingField(field))?;
+    match word.parse::<u32>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ParseModelineError::InvalidNumber {
+            field,
+            value: word.to_string(),
+        }),
+    }
+}
+
+/// Parse a decimal clock in MHz into Hz, without loss of precision.
+fn parse_clock_mhz(clock: &str) -> Option<i64> {
+    let (integer, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let integer: i64 = if integer.is_empty() {
+        0
+    } else {
+        integer.parse().ok()?
+    };
+    // Only the first 6 digits of the fraction matter at a 1 Hz precision,
+    // the 7th one is used for rounding.
+    let mut hz = 0;
+    for (i, digit) in fraction
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(7)
+        .enumerate()
+    {
+        let digit = (digit - b'0') as i64;
+        if i == 6 {
+            hz += (digit >= 5) as i64;
+        } else {
+            hz = hz * 10 + digit;
+        }
+    }
+    integer.checked_mul(1_000_000)?.checked_add(hz)
+}
+
+impl fmt::Display for Modeline {
+    /// Format the modeline with the `Modeline` keyword. The clock is printed
+    /// with at least two decimals, and as many as needed to be exact.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = &self.mode;
+        let mhz = mode.pixel_clock_hz / 1_000_000;
+        let mut fraction = format!("{:06}", mode.pixel_clock_hz % 1_000_000);
+        while fraction.len() > 2 && fraction.ends_with('0') {
+            fraction.pop();
+        }
+
+        write!(
+            f,
+            "Modeline \"{}\" {}.{} {} {} {} {} {} {} {} {}",
+            self.name,
+            mhz,
+            fraction,
+            mode.h_active,
+            mode.h_active + mode.h_front,
+            mode.h_active + mode.h_front + mode.h_sync,
+            mode.h_total(),
+            mode.v_active,
+            mode.v_active + mode.v_front,
+            mode.v_active + mode.v_front + mode.v_sync,
+            mode.v_total(),
+        )?;
+
+        f.write_str(match mode.h_sync_polarity {
+            SyncPolarity::Positive => " +hsync",
+            SyncPolarity::Negative => " -hsync",
+        })?;
+        f.write_str(match mode.v_sync_polarity {
+            SyncPolarity::Positive => " +vsync",
+            SyncPolarity::Negative => " -vsync",
+        })?;
+        if mode.interlaced {
+            f.write_str(" Interlace")?;
+        }
+        if self.double_scan {
+            f.write_str(" DoubleScan")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELINE_1080P60: &str =
+        "Modeline \"1920x1080\" 148.50 1920 2008 2052 2200 1080 1084 1089 1125 +hsync +vsync";
+
+    fn mode_1080p60() -> Mode {
+        Mode {
+            pixel_clock_hz: 148_500_000,
+            h_active: 1920,
+            h_front: 88,
+            h_sync: 44,
+            h_back: 148,
+            v_active: 1080,
+            v_front: 4,
+            v_sync: 5,
+            v_back: 36,
+            h_sync_polarity: SyncPolarity::Positive,
+            v_sync_polarity: SyncPolarity::Positive,
+            interlaced: false,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let modeline: Modeline = MODELINE_1080P60.parse().unwrap();
+        assert_eq!(modeline, Modeline::new(mode_1080p60()));
+        assert_eq!(modeline.to_string(), MODELINE_1080P60);
+
+        let mut mode = mode_1080p60();
+        mode.pixel_clock_hz = 74_175_824;
+        mode.h_sync_polarity = SyncPolarity::Negative;
+        mode.interlaced = true;
+        let modeline = Modeline {
+            name: "1920x1080i".to_string(),
+            mode,
+            double_scan: true,
+        };
+        let s = modeline.to_string();
+        assert_eq!(
+            s,
+            "Modeline \"1920x1080i\" 74.175824 1920 2008 2052 2200 1080 1084 1089 1125 -hsync \
+             +vsync Interlace DoubleScan"
+        );
+        assert_eq!(s.parse::<Modeline>().unwrap(), modeline);
+    }
+
+    #[test]
+    fn keyword_and_name() {
+        let modeline: Modeline = "  modeline\t\"1920x1080\" 148.5 1920 2008 2052 2200 1080 1084 \
+                                  1089 1125 +HSync +VSync"
+            .parse()
+            .unwrap();
+        assert_eq!(modeline.mode, mode_1080p60());
+
+        let modeline: Modeline = "Modeline\"a b\" 148.5 1920 2008 2052 2200 1080 1084 1089 1125"
+            .parse()
+            .unwrap();
+        assert_eq!(modeline.name, "a b");
+        assert_eq!(modeline.mode.h_sync_polarity, SyncPolarity::Negative);
+
+        // Without a separator, the keyword is part of the name.
+        let modeline: Modeline = "Modeline1080p 148.5 1920 2008 2052 2200 1080 1084 1089 1125"
+            .parse()
+            .unwrap();
+        assert_eq!(modeline.name, "Modeline1080p");
+    }
+
+    #[test]
+    fn ignored_flags() {
+        let modeline: Modeline = "\"720x480\" 13.5 720 739 801 858 480 488 494 525 -hsync -vsync \
+                                  Composite +CSync -csync HSkew 4 BCast VScan 2"
+            .parse()
+            .unwrap();
+        assert_eq!(modeline.mode.pixel_clock_hz, 13_500_000);
+        assert_eq!(modeline.mode.h_total(), 858);
+        assert!(!modeline.double_scan);
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |s: &str| s.parse::<Modeline>().unwrap_err();
+        assert_eq!(
+            parse("Modeline \"1920x1080 148.5"),
+            ParseModelineError::InvalidName
+        );
+        assert_eq!(
+            parse("\"a\" 148.5 1920 2008 2052 2200 1080 1084 1089"),
+            ParseModelineError::MissingField("vtotal")
+        );
+        assert_eq!(
+            parse("\"a\" 148,5 1920 2008 2052 2200 1080 1084 1089 1125"),
+            ParseModelineError::InvalidNumber {
+                field: "clock",
+                value: "148,5".to_string()
+            }
+        );
+        assert_eq!(
+            parse("\"a\" 148.5 1920 2052 2008 2200 1080 1084 1089 1125"),
+            ParseModelineError::InvalidTiming
+        );
+        assert_eq!(
+            parse("\"a\" 148.5 1920 2008 2052 2200 1080 1084 1089 1125 +sync"),
+            ParseModelineError::UnknownFlag("+sync".to_string())
+        );
+        assert_eq!(
+            parse("\"a\" 148.5 1920 2008 2052 2200 1080 1084 1089 1125 HSkew"),
+            ParseModelineError::MissingField("hskew")
+        );
+        assert_eq!(
+            parse("\"a\" 148.5 1920 2008 2052 2200 1080 1084 1089 1125 VScan x"),
+            ParseModelineError::InvalidNumber {
+                field: "vscan",
+                value: "x".to_string()
+            }
+        );
+    }
+}
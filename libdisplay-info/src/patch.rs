@@ -0,0 +1,586 @@
+//! Editing of existing EDID blobs.
+//!
+//! [`EdidPatch`] applies typed edits to the raw bytes of an EDID and its
+//! CTA-861 extension blocks, and re-emits a blob with valid checksums. This is
+//! mostly useful to generate firmware overrides for displays with broken EDIDs.
+use crate::{
+    cta::Svd,
+    edid::{
+        checksum, string_descriptor, DetailedTimingDef, DisplayRangeLimits, EncodeError,
+        DUMMY_DESCRIPTOR, EDID_HEADER,
+    },
+};
+
+/// Applying an edit to an EDID blob failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PatchError {
+    /// The blob doesn't start with an EDID base block
+    #[error("blob is not an EDID")]
+    InvalidEdid,
+    /// A value couldn't be encoded
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    /// There is no room left for the new item
+    #[error("no space left for {0}")]
+    NoSpace(&'static str),
+    /// The item to edit doesn't exist in the blob
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    /// The edit would leave the base block without a preferred timing
+    #[error("no detailed timing definition left for the preferred timing")]
+    NoPreferredTiming,
+}
+
+const BLOCK_SIZE: usize = 128;
+
+/// Offsets of the 18 byte descriptors in the base block.
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+
+const CTA_TAG: u8 = 0x02;
+
+/// IEEE OUIs, in the byte order used in data blocks.
+const OUI_HDMI: [u8; 3] = [0x03, 0x0c, 0x00];
+const OUI_HDR10_PLUS: [u8; 3] = [0x8b, 0x84, 0x90];
+const OUI_DOLBY: [u8; 3] = [0x46, 0xd0, 0x00];
+
+/// An EDID blob being edited.
+///
+/// Edits only touch the bytes they need to, everything else is kept as-is.
+/// Checksums are recomputed by [`EdidPatch::into_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdidPatch {
+    data: Vec<u8>,
+}
+
+impl EdidPatch {
+    /// Start editing an EDID blob.
+    ///
+    /// The blob must hold whole 128 byte blocks and start with the EDID header.
+    pub fn new(blob: &[u8]) -> Result<Self, PatchError> {
+        if blob.len() < BLOCK_SIZE || blob.len() % BLOCK_SIZE != 0 || blob[..8] != EDID_HEADER {
+            return Err(PatchError::InvalidEdid);
+        }
+
+        Ok(Self {
+            data: blob.to_vec(),
+        })
+    }
+
+    /// Get the edited blob, with fixed checksums.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        for block in self.data.chunks_mut(BLOCK_SIZE) {
+            block[BLOCK_SIZE - 1] = checksum(&block[..BLOCK_SIZE - 1]);
+        }
+        self.data
+    }
+
+    /// Add a detailed timing definition.
+    ///
+    /// The timing is stored in the first unused descriptor of the base block,
+    /// or after the detailed timing definitions of the first CTA-861
+    /// extension block with enough room left.
+    pub fn add_dtd(&mut self, timing: &DetailedTimingDef) -> Result<(), PatchError> {
+        let timing = timing.encode()?;
+
+        if let Some(offset) = self.free_descriptor() {
+            self.data[offset..offset + 18].copy_from_slice(&timing);
+            return Ok(());
+        }
+
+        for block in self.cta_blocks() {
+            let ext = &mut self.data[block..block + BLOCK_SIZE];
+            let end = cta_used_end(ext);
+            if end + 18 < BLOCK_SIZE {
+                if ext[2] == 0 {
+                    ext[2] = 4;
+                }
+                ext[end..end + 18].copy_from_slice(&timing);
+                return Ok(());
+            }
+        }
+
+        Err(PatchError::NoSpace("detailed timing definition"))
+    }
+
+    /// Remove a detailed timing definition.
+    ///
+    /// Timings are indexed in the order they appear in the blob: the base
+    /// block first, followed by the CTA-861 extension blocks. The following
+    /// timings of the base block move up one descriptor, so that the first
+    /// descriptor keeps holding the preferred timing, and the last one is
+    /// replaced with a dummy descriptor. Removing the preferred timing fails
+    /// if it is the only timing of the base block.
+    pub fn remove_dtd(&mut self, index: usize) -> Result<(), PatchError> {
+        let mut index = index;
+        let base_dtds: Vec<usize> = DESCRIPTOR_OFFSETS
+            .into_iter()
+            .filter(|offset| is_dtd(&self.data[*offset..*offset + 18]))
+            .collect();
+        if index < base_dtds.len() {
+            if base_dtds.len() == 1 && base_dtds[0] == DESCRIPTOR_OFFSETS[0] {
+                return Err(PatchError::NoPreferredTiming);
+            }
+            for pair in base_dtds[index..].windows(2) {
+                self.data.copy_within(pair[1]..pair[1] + 18, pair[0]);
+            }
+            let last = base_dtds[base_dtds.len() - 1];
+            self.data[last..last + 18].copy_from_slice(&DUMMY_DESCRIPTOR);
+            return Ok(());
+        }
+        index -= base_dtds.len();
+
+        for block in self.cta_blocks() {
+            let ext = &mut self.data[block..block + BLOCK_SIZE];
+            let dtds = cta_dtd_offsets(ext);
+            if let Some(offset) = dtds.get(index) {
+                cta_remove(ext, *offset, 18);
+                return Ok(());
+            }
+            index -= dtds.len();
+        }
+
+        Err(PatchError::NotFound("detailed timing definition"))
+    }
+
+    /// Replace the preferred timing, stored in the first descriptor of the
+    /// base block.
+    pub fn set_preferred_timing(&mut self, timing: &DetailedTimingDef) -> Result<(), PatchError> {
+        let offset = DESCRIPTOR_OFFSETS[0];
+        self.data[offset..offset + 18].copy_from_slice(&timing.encode()?);
+        Ok(())
+    }
+
+    /// Add a short video descriptor to the first video data block.
+    ///
+    /// A video data block is created in the first CTA-861 extension block if
+    /// there is none.
+    pub fn add_svd(&mut self, svd: &Svd) -> Result<(), PatchError> {
+        let svd = svd.encode()?;
+
+        let blocks = self.cta_blocks();
+        for block in &blocks {
+            let ext = &mut self.data[*block..*block + BLOCK_SIZE];
+            let video = cta_data_blocks(ext).find(|(offset, _)| ext[*offset] >> 5 == 2);
+            if let Some((offset, len)) = video {
+                if len == 31 {
+                    return Err(PatchError::NoSpace("short video descriptor"));
+                }
+                cta_insert(ext, offset + 1 + len, &[svd])?;
+                ext[offset] += 1;
+                return Ok(());
+            }
+        }
+
+        let Some(block) = blocks.first() else {
+            return Err(PatchError::NotFound("CTA-861 extension block"));
+        };
+        cta_insert(
+            &mut self.data[*block..*block + BLOCK_SIZE],
+            4,
+            &[2 << 5 | 1, svd],
+        )
+    }
+
+    /// Remove all short video descriptors with the given VIC.
+    ///
+    /// Video data blocks left empty are removed. The bits of the removed
+    /// descriptors are dropped from the YCbCr 4:2:0 capability map, so that it
+    /// keeps matching the remaining ones.
+    pub fn remove_svd(&mut self, vic: u8) -> Result<(), PatchError> {
+        let mut found = false;
+        for block in self.cta_blocks() {
+            let ext = &mut self.data[block..block + BLOCK_SIZE];
+            let removed: Vec<usize> = cta_data_blocks(ext)
+                .filter(|(offset, _)| ext[*offset] >> 5 == 2)
+                .flat_map(|(offset, len)| &ext[offset + 1..offset + 1 + len])
+                .enumerate()
+                .filter(|(_, svd)| svd_vic(**svd) == vic)
+                .map(|(index, _)| index)
+                .collect();
+
+            let mut data_blocks: Vec<_> = cta_data_blocks(ext).collect();
+            // Walk backwards, so removals don't shift the blocks still to visit.
+            data_blocks.reverse();
+            for (offset, len) in data_blocks {
+                if ext[offset] >> 5 != 2 {
+                    continue;
+                }
+
+                let mut remaining = len;
+                for i in (0..len).rev() {
+                    if svd_vic(ext[offset + 1 + i]) == vic {
+                        cta_remove(ext, offset + 1 + i, 1);
+                        ext[offset] -= 1;
+                        remaining -= 1;
+                        found = true;
+                    }
+                }
+                if remaining == 0 && len > 0 {
+                    cta_remove(ext, offset, 1);
+                }
+            }
+
+            let cap_map = cta_data_blocks(ext)
+                .find(|(offset, len)| ext[*offset] >> 5 == 7 && *len >= 1 && ext[offset + 1] == 15);
+            if let Some((offset, len)) = cap_map {
+                remove_bits(&mut ext[offset + 2..offset + 1 + len], &removed);
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(PatchError::NotFound("short video descriptor"))
+        }
+    }
+
+    /// Replace the display range limits descriptor, or add one in an unused
+    /// descriptor of the base block.
+    pub fn set_range_limits(&mut self, limits: &DisplayRangeLimits) -> Result<(), PatchError> {
+        self.set_display_descriptor(0xfd, limits.encode()?, "display range limits")
+    }
+
+    /// Replace the display product name descriptor, or add one in an unused
+    /// descriptor of the base block.
+    pub fn set_monitor_name(&mut self, name: &str) -> Result<(), PatchError> {
+        self.set_display_descriptor(0xfc, string_descriptor(0xfc, name)?, "display product name")
+    }
+
+    /// Set the CEC physical address in the HDMI vendor-specific data block.
+    ///
+    /// The address is given in its 16 bit form, e.g. `0x1000` for `1.0.0.0`.
+    pub fn set_cec_physical_address(&mut self, address: u16) -> Result<(), PatchError> {
+        let mut found = false;
+        for block in self.cta_blocks() {
+            let ext = &mut self.data[block..block + BLOCK_SIZE];
+            let vsdbs: Vec<_> = cta_data_blocks(ext)
+                .filter(|(offset, len)| {
+                    ext[*offset] >> 5 == 3 && *len >= 5 && ext[offset + 1..offset + 4] == OUI_HDMI
+                })
+                .collect();
+            for (offset, _) in vsdbs {
+                ext[offset + 4..offset + 6].copy_from_slice(&address.to_be_bytes());
+                found = true;
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(PatchError::NotFound("HDMI vendor-specific data block"))
+        }
+    }
+
+    /// Remove all HDR related data blocks: HDR static and dynamic metadata
+    /// data blocks, as well as HDR10+ and Dolby Vision vendor-specific video
+    /// data blocks.
+    pub fn strip_hdr(&mut self) {
+        self.strip_data_blocks(|block| match block {
+            [header, 6 | 7, ..] if header >> 5 == 7 => true,
+            [header, 1, oui @ ..] if header >> 5 == 7 && oui.len() >= 3 => {
+                oui[..3] == OUI_HDR10_PLUS || oui[..3] == OUI_DOLBY
+            }
+            _ => false,
+        });
+    }
+
+    /// Remove all audio related data blocks, and clear the basic audio flag.
+    pub fn strip_audio(&mut self) {
+        self.strip_data_blocks(|block| match block {
+            [header, ..] if matches!(header >> 5, 1 | 4) => true,
+            [header, 17..=20, ..] if header >> 5 == 7 => true,
+            _ => false,
+        });
+
+        for block in self.cta_blocks() {
+            self.data[block + 3] &= !(1 << 6);
+        }
+    }
+
+    /// Offsets of the CTA-861 extension blocks.
+    fn cta_blocks(&self) -> Vec<usize> {
+        (BLOCK_SIZE..self.data.len())
+            .step_by(BLOCK_SIZE)
+            .filter(|offset| self.data[*offset] == CTA_TAG)
+            .collect()
+    }
+
+    /// Offset of the first dummy descriptor of the base block.
+    fn free_descriptor(&self) -> Option<usize> {
+        DESCRIPTOR_OFFSETS
+            .into_iter()
+            .find(|offset| self.data[*offset..*offset + 5] == DUMMY_DESCRIPTOR[..5])
+    }
+
+    fn set_display_descriptor(
+        &mut self,
+        tag: u8,
+        descriptor: [u8; 18],
+        name: &'static str,
+    ) -> Result<(), PatchError> {
+        let existing = DESCRIPTOR_OFFSETS.into_iter().find(|offset| {
+            !is_dtd(&self.data[*offset..*offset + 18]) && self.data[offset + 3] == tag
+        });
+        let Some(offset) = existing.or_else(|| self.free_descriptor()) else {
+            return Err(PatchError::NoSpace(name));
+        };
+
+        self.data[offset..offset + 18].copy_from_slice(&descriptor);
+        Ok(())
+    }
+
+    /// Remove the data blocks of all CTA-861 extension blocks matching a
+    /// predicate. The predicate is given the data block, including its header.
+    fn strip_data_blocks(&mut self, predicate: impl Fn(&[u8]) -> bool) {
+        for block in self.cta_blocks() {
+            let ext = &mut self.data[block..block + BLOCK_SIZE];
+            let mut data_blocks: Vec<_> = cta_data_blocks(ext).collect();
+            data_blocks.reverse();
+            for (offset, len) in data_blocks {
+                if predicate(&ext[offset..offset + 1 + len]) {
+                    cta_remove(ext, offset, 1 + len);
+                }
+            }
+        }
+    }
+}
+
+fn is_dtd(descriptor: &[u8]) -> bool {
+    descriptor[0] != 0 || descriptor[1] != 0
+}
+
+/// Remove the bits at the given sorted indices from a little-endian bitmap,
+/// shifting the following bits down.
+fn remove_bits(bitmap: &mut [u8], indices: &[usize]) {
+    let bits = bitmap.len() * 8;
+    let kept: Vec<bool> = (0..bits)
+        .filter(|bit| indices.binary_search(bit).is_err())
+        .map(|bit| bitmap[bit / 8] & (1 << (bit % 8)) != 0)
+        .collect();
+    bitmap.fill(0);
+    for (bit, set) in kept.into_iter().enumerate() {
+        if set {
+            bitmap[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+}
+
+fn svd_vic(svd: u8) -> u8 {
+    match svd {
+        129..=192 => svd & 0x7f,
+        _ => svd,
+    }
+}
+
+/// Offset of the detailed timing definitions in a CTA-861 extension block.
+fn cta_dtd_start(ext: &[u8]) -> usize {
+    match ext[2] {
+        0 => 4,
+        d => (d as usize).clamp(4, BLOCK_SIZE - 1),
+    }
+}
+
+/// Iterate over the data blocks of a CTA-861 extension block, as offsets of
+/// the data block headers and payload lengths.
+fn cta_data_blocks(ext: &[u8]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let end = cta_dtd_start(ext);
+    let mut offset = 4;
+    std::iter::from_fn(move || {
+        if offset >= end {
+            return None;
+        }
+        let len = (ext[offset] & 0x1f) as usize;
+        let block = (offset, len.min(end - offset - 1));
+        offset += 1 + len;
+        Some(block)
+    })
+}
+
+/// Offsets of the detailed timing definitions of a CTA-861 extension block.
+fn cta_dtd_offsets(ext: &[u8]) -> Vec<usize> {
+    (cta_dtd_start(ext)..BLOCK_SIZE - 18)
+        .step_by(18)
+        .take_while(|offset| is_dtd(&ext[*offset..*offset + 18]))
+        .collect()
+}
+
+/// Offset of the padding at the end of a CTA-861 extension block.
+fn cta_used_end(ext: &[u8]) -> usize {
+    cta_dtd_start(ext) + 18 * cta_dtd_offsets(ext).len()
+}
+
+/// Insert bytes in the data block collection of a CTA-861 extension block,
+/// moving the detailed timing definitions.
+fn cta_insert(ext: &mut [u8], offset: usize, bytes: &[u8]) -> Result<(), PatchError> {
+    let end = cta_used_end(ext);
+    if end + bytes.len() >= BLOCK_SIZE {
+        return Err(PatchError::NoSpace("data block"));
+    }
+
+    ext.copy_within(offset..end, offset + bytes.len());
+    ext[offset..offset + bytes.len()].copy_from_slice(bytes);
+    ext[2] = (cta_dtd_start(ext) + bytes.len()) as u8;
+    Ok(())
+}
+
+/// Remove bytes from a CTA-861 extension block, moving the following bytes
+/// and updating the offset of the detailed timing definitions if needed.
+fn cta_remove(ext: &mut [u8], offset: usize, len: usize) {
+    let dtd_start = cta_dtd_start(ext);
+    ext.copy_within(offset + len..BLOCK_SIZE - 1, offset);
+    ext[BLOCK_SIZE - 1 - len..BLOCK_SIZE - 1].fill(0);
+    if offset < dtd_start {
+        ext[2] = (dtd_start - len) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cta::{
+            tests::{sad, svd},
+            AudioFormat, CtaBuilder, Flags,
+        },
+        edid::{tests::*, EdidBuilder},
+        validate::validate,
+    };
+
+    /// Offset of the CTA-861 extension block in the test blob.
+    const EXT: usize = BLOCK_SIZE;
+
+    /// An EDID with a CTA-861 extension block holding, in order, a video,
+    /// an audio, an HDMI vendor-specific and an HDR static metadata data
+    /// block, followed by a detailed timing definition at offset 21.
+    fn edid() -> Vec<u8> {
+        let base = EdidBuilder::new(vendor_product())
+            .detailed_timing_def(dtd_1080p60())
+            .product_name("Test")
+            .extension_count(1)
+            .build()
+            .unwrap();
+        let cta = CtaBuilder::new()
+            .flags(Flags {
+                it_underscan: false,
+                basic_audio: true,
+                ycc444: false,
+                ycc422: false,
+                native_dtds: 1,
+            })
+            .video(&[svd(16, true), svd(4, false)])
+            .audio(&[sad(AudioFormat::LPCM, 2)])
+            .data_block(3, &[0x03, 0x0c, 0x00, 0x10, 0x00])
+            .extended_data_block(6, &[0x05, 0x01])
+            .detailed_timing_def(dtd_1080p60())
+            .build()
+            .unwrap();
+        [base, cta].concat()
+    }
+
+    fn patched(edit: impl FnOnce(&mut EdidPatch)) -> Vec<u8> {
+        let mut patch = EdidPatch::new(&edid()).unwrap();
+        edit(&mut patch);
+        let blob = patch.into_bytes();
+        assert_eq!(validate(&blob), []);
+        blob
+    }
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(patched(|_| {}), edid());
+        assert_eq!(EdidPatch::new(&edid()[..100]), Err(PatchError::InvalidEdid));
+    }
+
+    #[test]
+    fn dtds() {
+        let timing = dtd_1080p60().encode().unwrap();
+
+        let blob = patched(|patch| patch.add_dtd(&dtd_1080p60()).unwrap());
+        assert_eq!(blob[90..108], timing);
+
+        let mut patch = EdidPatch::new(&edid()).unwrap();
+        assert_eq!(patch.remove_dtd(0), Err(PatchError::NoPreferredTiming));
+
+        let mut second = dtd_1080p60();
+        second.pixel_clock_hz = 74_250_000;
+        let blob = patched(|patch| {
+            patch.add_dtd(&second).unwrap();
+            patch.remove_dtd(0).unwrap();
+        });
+        assert_eq!(blob[54..72], second.encode().unwrap());
+        assert_eq!(blob[72..90], string_descriptor(0xfc, "Test").unwrap());
+        assert_eq!(blob[90..108], DUMMY_DESCRIPTOR);
+
+        let blob = patched(|patch| patch.remove_dtd(1).unwrap());
+        assert_eq!(blob[EXT + 2], 21);
+        assert!(blob[EXT + 21..EXT + 127].iter().all(|&b| b == 0));
+
+        let mut patch = EdidPatch::new(&edid()).unwrap();
+        assert_eq!(
+            patch.remove_dtd(2),
+            Err(PatchError::NotFound("detailed timing definition"))
+        );
+    }
+
+    #[test]
+    fn svds() {
+        let blob = patched(|patch| patch.add_svd(&svd(3, false)).unwrap());
+        assert_eq!(blob[EXT + 2], 22);
+        assert_eq!(blob[EXT + 4..EXT + 8], [0x43, 0x90, 0x04, 0x03]);
+        assert_eq!(blob[EXT + 22..EXT + 40], dtd_1080p60().encode().unwrap());
+
+        let blob = patched(|patch| patch.remove_svd(16).unwrap());
+        assert_eq!(blob[EXT + 2], 20);
+        assert_eq!(blob[EXT + 4..EXT + 7], [0x41, 0x04, 0x23]);
+
+        let blob = patched(|patch| {
+            patch.remove_svd(16).unwrap();
+            patch.remove_svd(4).unwrap();
+        });
+        assert_eq!(blob[EXT + 2], 18);
+        assert_eq!(blob[EXT + 4], 0x23);
+
+        let mut patch = EdidPatch::new(&edid()).unwrap();
+        assert_eq!(
+            patch.remove_svd(3),
+            Err(PatchError::NotFound("short video descriptor"))
+        );
+
+        // The YCbCr 4:2:0 capability map flags VICs 4 and 3.
+        let cta = CtaBuilder::new()
+            .video(&[svd(16, true), svd(4, false), svd(3, false)])
+            .extended_data_block(15, &[0b110])
+            .build()
+            .unwrap();
+        let blob = [&edid()[..BLOCK_SIZE], &cta[..]].concat();
+        let mut patch = EdidPatch::new(&blob).unwrap();
+        patch.remove_svd(4).unwrap();
+        let blob = patch.into_bytes();
+        assert_eq!(blob[EXT + 4..EXT + 10], [0x42, 0x90, 0x03, 0xe2, 15, 0b10]);
+    }
+
+    #[test]
+    fn descriptors() {
+        let blob = patched(|patch| patch.set_monitor_name("Other").unwrap());
+        assert_eq!(blob[72..90], string_descriptor(0xfc, "Other").unwrap());
+        assert_eq!(blob[90..108], DUMMY_DESCRIPTOR);
+
+        let blob = patched(|patch| patch.set_preferred_timing(&dtd_1080p60()).unwrap());
+        assert_eq!(blob[..BLOCK_SIZE - 1], edid()[..BLOCK_SIZE - 1]);
+    }
+
+    #[test]
+    fn data_blocks() {
+        let blob = patched(|patch| patch.set_cec_physical_address(0x2100).unwrap());
+        assert_eq!(blob[EXT + 15..EXT + 17], [0x21, 0x00]);
+
+        let blob = patched(|patch| patch.strip_audio());
+        assert_eq!(blob[EXT + 2], 17);
+        assert_eq!(blob[EXT + 3] & 1 << 6, 0);
+        assert_eq!(blob[EXT + 7], 0x65);
+
+        let blob = patched(|patch| patch.strip_hdr());
+        assert_eq!(blob[EXT + 2], 17);
+        assert_eq!(blob[EXT + 17..EXT + 35], dtd_1080p60().encode().unwrap());
+    }
+}
@@ -0,0 +1,382 @@
+//! Extraction of EDID blobs from textual dumps.
+//!
+//! EDIDs are often shared as text rather than as binary files. This module
+//! recognizes the output of `xrandr --verbose`, `edid-decode`, Xorg logs and
+//! `hexdump -C`, as well as plain hexadecimal, and turns it back into the raw
+//! blob expected by [`Info::parse_edid`](crate::info::Info::parse_edid).
+use std::fmt;
+
+/// Textual format an EDID dump was recognized as
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextFormat {
+    /// `EDID:` property of `xrandr --verbose`
+    XrandrVerbose,
+    /// Hex dump printed by `edid-decode`
+    EdidDecode,
+    /// `EDID (in hex):` section of an Xorg log
+    XorgLog,
+    /// Canonical `hexdump -C` output
+    HexdumpC,
+    /// Bare hexadecimal bytes
+    PlainHex,
+}
+
+impl fmt::Display for TextFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TextFormat::XrandrVerbose => "xrandr --verbose",
+            TextFormat::EdidDecode => "edid-decode",
+            TextFormat::XorgLog => "Xorg log",
+            TextFormat::HexdumpC => "hexdump -C",
+            TextFormat::PlainHex => "plain hex",
+        })
+    }
+}
+
+/// Reason a textual EDID dump could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseTextErrorKind {
+    /// No EDID bytes were found
+    #[error("no EDID data found")]
+    NoData,
+    /// A character which is not a hexadecimal digit was found
+    #[error("invalid hex digit {0:?}")]
+    InvalidHexDigit(char),
+    /// A hex string has an odd number of digits
+    #[error("odd number of hex digits")]
+    OddDigitCount,
+    /// A `hexdump -C` offset does not match the amount of data read so far
+    #[error("unexpected offset {found:#x}, expected {expected:#x}")]
+    UnexpectedOffset { expected: usize, found: usize },
+    /// The extracted blob is not a whole number of 128 byte blocks
+    #[error("blob length {0} is not a multiple of 128")]
+    UnalignedLength(usize),
+}
+
+/// Decoding a textual EDID dump failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Parsing {format} EDID dump failed at line {line}: {kind}")]
+pub struct ParseTextError {
+    /// Format the input was detected or requested as
+    pub format: TextFormat,
+    /// 1-based line number the error was found at
+    pub line: usize,
+    pub kind: ParseTextErrorKind,
+}
+
+/// Detect the format of a textual EDID dump.
+pub fn detect(input: &str) -> TextFormat {
+    let mut first_line = None;
+    for line in input.lines() {
+        let trimmed = line.trim();
+        if trimmed.ends_with("EDID (in hex):") {
+            return TextFormat::XorgLog;
+        }
+        if trimmed == "EDID:" {
+            return TextFormat::XrandrVerbose;
+        }
+        if trimmed.starts_with("edid-decode") {
+            return TextFormat::EdidDecode;
+        }
+        if first_line.is_none() && !trimmed.is_empty() {
+            first_line = Some(trimmed);
+        }
+    }
+
+    match first_line {
+        Some(line) if hexdump_offset(line).is_some() => TextFormat::HexdumpC,
+        _ => TextFormat::PlainHex,
+    }
+}
+
+/// Extract an EDID blob from a textual dump, detecting its format.
+///
+/// See [`detect`] for the format detection.
+pub fn parse(input: &str) -> Result<Vec<u8>, ParseTextError> {
+    parse_as(input, detect(input))
+}
+
+/// Extract an EDID blob from a textual dump in the given format.
+///
+/// Dumps containing several EDIDs, like `xrandr --verbose` with multiple
+/// outputs, yield the first one.
+pub fn parse_as(input: &str, format: TextFormat) -> Result<Vec<u8>, ParseTextError> {
+    let mut parser = Parser {
+        format,
+        bytes: Vec::new(),
+        line: 0,
+    };
+
+    match format {
+        TextFormat::XrandrVerbose => {
+            parser.parse_section(input, |line| line.trim() == "EDID:", str::trim)?
+        }
+        TextFormat::XorgLog => parser.parse_section(
+            input,
+            |line| line.trim_end().ends_with("EDID (in hex):"),
+            |line| line.split_once("): ").map_or("", |(_, data)| data.trim()),
+        )?,
+        TextFormat::EdidDecode => parser.parse_edid_decode(input)?,
+        TextFormat::HexdumpC => parser.parse_hexdump(input)?,
+        TextFormat::PlainHex => parser.parse_plain(input)?,
+    }
+
+    parser.finish()
+}
+
+struct Parser {
+    format: TextFormat,
+    bytes: Vec<u8>,
+    line: usize,
+}
+
+impl Parser {
+    fn error(&self, kind: ParseTextErrorKind) -> ParseTextError {
+        ParseTextError {
+            format: self.format,
+            line: self.line,
+            kind,
+        }
+    }
+
+    fn push_hex(&mut self, hex: &str) -> Result<(), ParseTextError> {
+        let mut high = None;
+        for c in hex.chars().filter(|c| !c.is_whitespace()) {
+            let Some(digit) = c.to_digit(16) else {
+                return Err(self.error(ParseTextErrorKind::InvalidHexDigit(c)));
+            };
+            match high.take() {
+                Some(high) => self.bytes.push((high << 4 | digit) as u8),
+                None => high = Some(digit),
+            }
+        }
+
+        if high.is_some() {
+            return Err(self.error(ParseTextErrorKind::OddDigitCount));
+        }
+        Ok(())
+    }
+
+    /// Parse the hex lines following a header line, up to the first line
+    /// which isn't hex.
+    fn parse_section(
+        &mut self,
+        input: &str,
+        is_header: impl Fn(&str) -> bool,
+        data: impl Fn(&str) -> &str,
+    ) -> Result<(), ParseTextError> {
+        let mut in_section = false;
+        for (index, line) in input.lines().enumerate() {
+            self.line = index + 1;
+            if !in_section {
+                in_section = is_header(line);
+                continue;
+            }
+
+            let data = data(line);
+            if data.is_empty() || !data.chars().all(|c| c.is_ascii_hexdigit()) {
+                break;
+            }
+            self.push_hex(data)?;
+        }
+        Ok(())
+    }
+
+    fn parse_edid_decode(&mut self, input: &str) -> Result<(), ParseTextError> {
+        for (index, line) in input.lines().enumerate() {
+            self.line = index + 1;
+            let line = line.trim();
+            if line.starts_with("edid-decode") || line.is_empty() {
+                continue;
+            }
+            if line.starts_with("----") {
+                break;
+            }
+            self.push_hex(line)?;
+        }
+        Ok(())
+    }
+
+    fn parse_hexdump(&mut self, input: &str) -> Result<(), ParseTextError> {
+        let mut previous: Vec<u8> = Vec::new();
+        let mut repeat = false;
+        for (index, line) in input.lines().enumerate() {
+            self.line = index + 1;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "*" {
+                repeat = true;
+                continue;
+            }
+
+            let Some(offset) = hexdump_offset(line) else {
+                return Err(self.error(ParseTextErrorKind::InvalidHexDigit(
+                    line.chars().find(|c| !c.is_ascii_hexdigit()).unwrap_or(' '),
+                )));
+            };
+            if repeat && !previous.is_empty() {
+                while self.bytes.len() < offset {
+                    self.bytes.extend_from_slice(&previous);
+                }
+                repeat = false;
+            }
+            if offset != self.bytes.len() {
+                return Err(self.error(ParseTextErrorKind::UnexpectedOffset {
+                    expected: self.bytes.len(),
+                    found: offset,
+                }));
+            }
+
+            let data = &line[8..];
+            let data = data.split_once('|').map_or(data, |(hex, _)| hex);
+            let start = self.bytes.len();
+            self.push_hex(data)?;
+            previous = self.bytes[start..].to_vec();
+        }
+        Ok(())
+    }
+
+    fn parse_plain(&mut self, input: &str) -> Result<(), ParseTextError> {
+        for (index, line) in input.lines().enumerate() {
+            self.line = index + 1;
+            for word in line.split(|c: char| c.is_whitespace() || c == ',') {
+                let word = word
+                    .strip_prefix("0x")
+                    .or_else(|| word.strip_prefix("0X"))
+                    .unwrap_or(word);
+                self.push_hex(word)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<u8>, ParseTextError> {
+        if self.bytes.is_empty() {
+            return Err(self.error(ParseTextErrorKind::NoData));
+        }
+        if self.bytes.len() % 128 != 0 {
+            return Err(self.error(ParseTextErrorKind::UnalignedLength(self.bytes.len())));
+        }
+        Ok(self.bytes)
+    }
+}
+
+/// Offset at the start of a `hexdump -C` line
+fn hexdump_offset(line: &str) -> Option<usize> {
+    let offset = line.get(..8)?;
+    let rest = &line[8..];
+    if !(rest.is_empty() || rest.starts_with("  ")) {
+        return None;
+    }
+    usize::from_str_radix(offset, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob() -> Vec<u8> {
+        let mut blob: Vec<u8> = (0..128).map(|i| (i * 7) as u8).collect();
+        blob[..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+        blob
+    }
+
+    fn hex_lines(bytes: &[u8], prefix: &str) -> String {
+        bytes
+            .chunks(16)
+            .map(|chunk| {
+                let hex: String = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+                format!("{prefix}{hex}\n")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn xrandr_verbose() {
+        let input = format!(
+            "HDMI-1 connected 1920x1080+0+0\n\tEDID:\n{}\tBrightness: 1.0\n",
+            hex_lines(&blob(), "\t\t")
+        );
+        assert_eq!(detect(&input), TextFormat::XrandrVerbose);
+        assert_eq!(parse(&input), Ok(blob()));
+    }
+
+    #[test]
+    fn xorg_log() {
+        let input = format!(
+            "[    12.345] (II) modeset(0): EDID (in hex):\n{}[    12.346] (II) modeset(0): Printing probed modes\n",
+            hex_lines(&blob(), "[    12.345] (II) modeset(0): \t")
+        );
+        assert_eq!(detect(&input), TextFormat::XorgLog);
+        assert_eq!(parse(&input), Ok(blob()));
+    }
+
+    #[test]
+    fn edid_decode() {
+        let input = format!(
+            "edid-decode (hex):\n\n{}\n----------------\n\nBlock 0, Base EDID:\n",
+            hex_lines(&blob(), "")
+        );
+        assert_eq!(detect(&input), TextFormat::EdidDecode);
+        assert_eq!(parse(&input), Ok(blob()));
+    }
+
+    #[test]
+    fn hexdump_c_with_repeated_lines() {
+        let mut expected = blob();
+        expected[16..64].fill(0xaa);
+        let input = format!(
+            "00000000  {}  |........|\n00000010  {}  |........|\n*\n{}00000080\n",
+            hex_bytes(&expected[..16]),
+            hex_bytes(&expected[16..32]),
+            (64..128)
+                .step_by(16)
+                .map(|offset| format!(
+                    "{offset:08x}  {}  |........|\n",
+                    hex_bytes(&expected[offset..offset + 16])
+                ))
+                .collect::<String>(),
+        );
+        assert_eq!(detect(&input), TextFormat::HexdumpC);
+        assert_eq!(parse(&input), Ok(expected));
+    }
+
+    fn hex_bytes(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn plain_hex() {
+        let input = blob()
+            .iter()
+            .map(|byte| format!("0x{byte:02X},"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(detect(&input), TextFormat::PlainHex);
+        assert_eq!(parse(&input), Ok(blob()));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &str| parse(input).unwrap_err().kind;
+        assert_eq!(error(""), ParseTextErrorKind::NoData);
+        assert_eq!(error("00ff\n0g"), ParseTextErrorKind::InvalidHexDigit('g'));
+        assert_eq!(error("00f"), ParseTextErrorKind::OddDigitCount);
+        assert_eq!(error("00ff"), ParseTextErrorKind::UnalignedLength(2));
+        assert_eq!(
+            parse_as("00000010  00 ff", TextFormat::HexdumpC)
+                .unwrap_err()
+                .kind,
+            ParseTextErrorKind::UnexpectedOffset {
+                expected: 0,
+                found: 0x10
+            }
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Structural validation of raw EDID blobs.
+//!
+//! The checks only look at the framing of the blob: header, checksums,
+//! extension count and block maps. They run without libdisplay-info, and can
+//! be used to find out why [`Info::parse_edid`](crate::info::Info::parse_edid)
+//! rejects a blob. [`repair`] fixes the issues which have a single sensible
+//! fix.
+use crate::edid::{checksum, EDID_HEADER};
+
+const BLOCK_SIZE: usize = 128;
+
+const BLOCK_MAP_TAG: u8 = 0xf0;
+
+/// Structural issue found in an EDID blob
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Issue {
+    /// The blob doesn't start with the fixed 8 byte EDID header
+    #[error("invalid EDID header")]
+    BadHeader,
+    /// The blob isn't made of whole 128 byte blocks
+    #[error("blob length {len} is not a multiple of 128")]
+    Truncated { len: usize },
+    /// The extension count of the base block doesn't match the number of
+    /// blocks in the blob
+    #[error("base block declares {declared} extensions, blob holds {actual}")]
+    ExtensionCountMismatch { declared: u8, actual: usize },
+    /// The checksum byte of a block is wrong
+    #[error("block {block} checksum is {found:#04x}, expected {expected:#04x}")]
+    ChecksumMismatch {
+        block: usize,
+        expected: u8,
+        found: u8,
+    },
+    /// A block map entry doesn't match the tag of the block it describes
+    #[error("block map entry for block {block} is {expected:#04x}, block tag is {found:#04x}")]
+    BlockMapMismatch {
+        block: usize,
+        expected: u8,
+        found: u8,
+    },
+    /// A block map is stored at a position where none is allowed
+    #[error("unexpected block map at block {block}")]
+    MisplacedBlockMap { block: usize },
+}
+
+/// Check the structure of an EDID blob.
+///
+/// Returns an empty list if no issue was found.
+pub fn validate(blob: &[u8]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if blob.len() < EDID_HEADER.len() || blob[..EDID_HEADER.len()] != EDID_HEADER {
+        issues.push(Issue::BadHeader);
+    }
+    if blob.len() < BLOCK_SIZE || blob.len() % BLOCK_SIZE != 0 {
+        issues.push(Issue::Truncated { len: blob.len() });
+    }
+    if blob.len() < BLOCK_SIZE {
+        return issues;
+    }
+
+    let blocks: Vec<&[u8]> = blob.chunks_exact(BLOCK_SIZE).collect();
+    let declared = blocks[0][126];
+    if declared as usize != blocks.len() - 1 {
+        issues.push(Issue::ExtensionCountMismatch {
+            declared,
+            actual: blocks.len() - 1,
+        });
+    }
+
+    for (index, block) in blocks.iter().enumerate() {
+        let expected = checksum(&block[..BLOCK_SIZE - 1]);
+        if block[BLOCK_SIZE - 1] != expected {
+            issues.push(Issue::ChecksumMismatch {
+                block: index,
+                expected,
+                found: block[BLOCK_SIZE - 1],
+            });
+        }
+    }
+
+    validate_block_maps(&blocks, &mut issues);
+
+    issues
+}
+
+/// Block maps are only used when there is more than one extension block. The
+/// first one is block 1 and describes blocks 2 to 127, the second one is
+/// block 128 and describes blocks 129 to 254.
+fn validate_block_maps(blocks: &[&[u8]], issues: &mut Vec<Issue>) {
+    let has_block_maps = blocks.len() > 2 && blocks[1][0] == BLOCK_MAP_TAG;
+
+    for (index, block) in blocks.iter().enumerate().skip(1) {
+        if block[0] != BLOCK_MAP_TAG {
+            continue;
+        }
+        if !has_block_maps || (index != 1 && index != 128) {
+            issues.push(Issue::MisplacedBlockMap { block: index });
+            continue;
+        }
+
+        for (i, entry) in block[1..BLOCK_SIZE - 1].iter().enumerate() {
+            let described = index + 1 + i;
+            let found = blocks.get(described).map_or(0, |block| block[0]);
+            if *entry != found {
+                issues.push(Issue::BlockMapMismatch {
+                    block: described,
+                    expected: *entry,
+                    found,
+                });
+            }
+        }
+    }
+}
+
+/// Fix the structural issues which can be fixed deterministically.
+///
+/// The header is rewritten, a trailing partial block is dropped, the extension
+/// count is set to the number of extension blocks and all checksums are
+/// recomputed. Block maps are left untouched.
+pub fn repair(blob: &[u8]) -> Vec<u8> {
+    let mut data = blob[..blob.len() - blob.len() % BLOCK_SIZE].to_vec();
+    if data.is_empty() {
+        data.resize(BLOCK_SIZE, 0);
+    }
+
+    data[..EDID_HEADER.len()].copy_from_slice(&EDID_HEADER);
+    data[126] = (data.len() / BLOCK_SIZE - 1).min(0xff) as u8;
+    for block in data.chunks_mut(BLOCK_SIZE) {
+        block[BLOCK_SIZE - 1] = checksum(&block[..BLOCK_SIZE - 1]);
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A blob with a base block and the given extension block tags.
+    fn blob(tags: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; BLOCK_SIZE * (tags.len() + 1)];
+        for (block, tag) in data.chunks_mut(BLOCK_SIZE).skip(1).zip(tags) {
+            block[0] = *tag;
+        }
+        repair(&data)
+    }
+
+    #[test]
+    fn valid() {
+        let data = blob(&[0x02]);
+        assert_eq!(data[..8], EDID_HEADER);
+        assert_eq!(data[126], 1);
+        assert_eq!(validate(&data), []);
+        assert_eq!(repair(&data), data);
+    }
+
+    #[test]
+    fn single_byte_corruption() {
+        let mut data = blob(&[0x02]);
+        data[BLOCK_SIZE + 10] ^= 0x40;
+        assert_eq!(
+            validate(&data),
+            [Issue::ChecksumMismatch {
+                block: 1,
+                expected: data[2 * BLOCK_SIZE - 1].wrapping_sub(0x40),
+                found: data[2 * BLOCK_SIZE - 1],
+            }]
+        );
+
+        let mut data = blob(&[]);
+        data[3] = 0;
+        let issues = validate(&data);
+        assert_eq!(issues[0], Issue::BadHeader);
+        assert!(matches!(
+            issues[1],
+            Issue::ChecksumMismatch { block: 0, .. }
+        ));
+        assert_eq!(validate(&repair(&data)), []);
+    }
+
+    #[test]
+    fn framing() {
+        let data = blob(&[0x02, 0x02]);
+        assert_eq!(
+            validate(&data[..2 * BLOCK_SIZE]),
+            [Issue::ExtensionCountMismatch {
+                declared: 2,
+                actual: 1
+            }]
+        );
+
+        let truncated = &data[..2 * BLOCK_SIZE + 10];
+        assert_eq!(
+            validate(truncated)[..2],
+            [
+                Issue::Truncated {
+                    len: 2 * BLOCK_SIZE + 10
+                },
+                Issue::ExtensionCountMismatch {
+                    declared: 2,
+                    actual: 1
+                }
+            ]
+        );
+        let repaired = repair(truncated);
+        assert_eq!(repaired.len(), 2 * BLOCK_SIZE);
+        assert_eq!(validate(&repaired), []);
+
+        assert_eq!(
+            validate(&EDID_HEADER),
+            [Issue::Truncated {
+                len: EDID_HEADER.len()
+            }]
+        );
+    }
+
+    #[test]
+    fn block_maps() {
+        let mut data = blob(&[BLOCK_MAP_TAG, 0x02, 0x70]);
+        data[BLOCK_SIZE + 1..BLOCK_SIZE + 3].copy_from_slice(&[0x02, 0x70]);
+        let data = repair(&data);
+        assert_eq!(validate(&data), []);
+
+        let mut mismatch = data.clone();
+        mismatch[BLOCK_SIZE + 2] = 0x02;
+        assert_eq!(
+            validate(&repair(&mismatch)),
+            [Issue::BlockMapMismatch {
+                block: 3,
+                expected: 0x02,
+                found: 0x70
+            }]
+        );
+
+        assert_eq!(
+            validate(&blob(&[0x02, BLOCK_MAP_TAG])),
+            [Issue::MisplacedBlockMap { block: 2 }]
+        );
+    }
+}